@@ -0,0 +1,230 @@
+use bevy::input::{keyboard::KeyCode, ButtonInput};
+use bevy::math::{Rect, Vec2};
+use bevy::prelude::{
+    default, Commands, Component, EventReader, Gizmos, Query, Res, ResMut, Resource, With,
+};
+use bevy::render::{color::Color, view::Visibility};
+use bevy::text::{Text, TextStyle};
+use bevy::ui::{node_bundles::TextBundle, PositionType, Style, Val};
+
+use super::{ArrowHitEvent, ArrowShotEvent, EnemyArea, EnemyKilledEvent};
+
+const HEATMAP_COLUMNS: usize = 16;
+const HEATMAP_ROWS: usize = 12;
+const OVERLAY_KEY: KeyCode = KeyCode::F3;
+
+/// Kill counts on a grid laid over `EnemyArea`. The cells are relative to the area,
+/// so the heatmap still fits after the window was resized
+#[derive(Clone, PartialEq, Debug)]
+pub struct Heatmap {
+    cells: Vec<u32>,
+}
+
+impl Default for Heatmap {
+    fn default() -> Self {
+        Heatmap {
+            cells: vec![0; HEATMAP_COLUMNS * HEATMAP_ROWS],
+        }
+    }
+}
+
+impl Heatmap {
+    /// The cell `pos` falls into, positions outside of `rect` count for the closest cell
+    fn cell(rect: Rect, pos: Vec2) -> (usize, usize) {
+        let relative = ((pos - rect.min) / rect.size()).clamp(Vec2::ZERO, Vec2::ONE);
+        let column = ((relative.x * HEATMAP_COLUMNS as f32) as usize).min(HEATMAP_COLUMNS - 1);
+        let row = ((relative.y * HEATMAP_ROWS as f32) as usize).min(HEATMAP_ROWS - 1);
+        (column, row)
+    }
+
+    pub fn add(&mut self, rect: Rect, pos: Vec2) {
+        let (column, row) = Heatmap::cell(rect, pos);
+        self.cells[row * HEATMAP_COLUMNS + column] += 1;
+    }
+
+    pub fn max(&self) -> u32 {
+        self.cells.iter().copied().max().unwrap_or(0)
+    }
+
+    /// The center, size and kill count of every cell with kills in it
+    pub fn hot_cells(&self, rect: Rect) -> impl Iterator<Item = (Vec2, Vec2, u32)> + '_ {
+        let size = rect.size() / Vec2::new(HEATMAP_COLUMNS as f32, HEATMAP_ROWS as f32);
+        self.cells
+            .iter()
+            .enumerate()
+            .filter(|(_, kills)| **kills > 0)
+            .map(move |(index, kills)| {
+                let cell = Vec2::new(
+                    (index % HEATMAP_COLUMNS) as f32,
+                    (index / HEATMAP_COLUMNS) as f32,
+                );
+                (rect.min + (cell + 0.5) * size, size, *kills)
+            })
+    }
+}
+
+/// Numbers for balancing enemy speed and gravity, collected over the whole session
+#[derive(Resource, Default, Debug)]
+pub struct Analytics {
+    pub shots: u32,
+    /// Shots that hit at least one enemy, however many arrows they split into
+    pub hits: u32,
+    total_pull_time: f32,
+    /// Summed over the first hit of every shot
+    total_hit_distance: f32,
+    pub heatmap: Heatmap,
+}
+
+impl Analytics {
+    /// Shots that hit per shot, at most 1
+    pub fn hit_rate(&self) -> f32 {
+        ratio(self.hits as f32, self.shots)
+    }
+
+    /// Seconds the bow was drawn per shot
+    pub fn average_pull_time(&self) -> f32 {
+        ratio(self.total_pull_time, self.shots)
+    }
+
+    /// How far arrows flew before they hit
+    pub fn average_hit_distance(&self) -> f32 {
+        ratio(self.total_hit_distance, self.hits)
+    }
+}
+
+fn ratio(total: f32, count: u32) -> f32 {
+    if count == 0 {
+        return 0.;
+    }
+    total / count as f32
+}
+
+/// Whether the heatmap and the numbers are drawn
+#[derive(Resource, Default)]
+pub struct AnalyticsOverlay(pub bool);
+
+#[derive(Component)]
+pub struct AnalyticsUi;
+
+pub fn collect_analytics(
+    enemy_area: Res<EnemyArea>,
+    mut shots: EventReader<ArrowShotEvent>,
+    mut hits: EventReader<ArrowHitEvent>,
+    mut kills: EventReader<EnemyKilledEvent>,
+    mut analytics: ResMut<Analytics>,
+) {
+    // arrows split off another one were not shot by the player
+    for pull_time in shots.read().filter_map(|shot| shot.pull_time) {
+        analytics.shots += 1;
+        analytics.total_pull_time += pull_time;
+    }
+    // one hit per shot, so split arrows don't push the hit rate past 1
+    for hit in hits.read().filter(|hit| hit.first) {
+        analytics.hits += 1;
+        analytics.total_hit_distance += hit.shot_distance;
+    }
+    for kill in kills.read() {
+        analytics.heatmap.add(enemy_area.rect, kill.pos);
+    }
+}
+
+pub fn spawn_analytics_ui(mut commands: Commands) {
+    commands.spawn((
+        AnalyticsUi,
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 16.,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(5.),
+            right: Val::Px(5.),
+            ..default()
+        }),
+    ));
+}
+
+pub fn toggle_analytics_overlay(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut overlay: ResMut<AnalyticsOverlay>,
+    mut ui: Query<&mut Visibility, With<AnalyticsUi>>,
+) {
+    if keys.just_pressed(OVERLAY_KEY) {
+        overlay.0 = !overlay.0;
+    }
+    for mut visibility in &mut ui {
+        *visibility = if overlay.0 {
+            Visibility::Visible
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
+
+pub fn update_analytics_ui(
+    analytics: Res<Analytics>,
+    mut query: Query<&mut Text, With<AnalyticsUi>>,
+) {
+    for mut text in &mut query {
+        text.sections[0].value = format!(
+            "Shots: {}  Hits: {}  Hit rate: {:.0}%\nAverage pull: {:.2}s  Average hit distance: {:.0}",
+            analytics.shots,
+            analytics.hits,
+            analytics.hit_rate() * 100.,
+            analytics.average_pull_time(),
+            analytics.average_hit_distance()
+        );
+    }
+}
+
+/// Every cell with kills gets a square, bigger and redder the more kills there were
+pub fn draw_heatmap(
+    overlay: Res<AnalyticsOverlay>,
+    enemy_area: Res<EnemyArea>,
+    analytics: Res<Analytics>,
+    mut gizmos: Gizmos,
+) {
+    if !overlay.0 {
+        return;
+    }
+    let max = analytics.heatmap.max().max(1) as f32;
+    for (center, size, kills) in analytics.heatmap.hot_cells(enemy_area.rect) {
+        let heat = kills as f32 / max;
+        let color = Color::rgb(heat, 0.2, 1. - heat);
+        gizmos.rect_2d(center, 0., size, color.with_a(0.3));
+        gizmos.rect_2d(center, 0., size * heat, color);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn heatmap_cells_follow_the_area() {
+        let mut heatmap = Heatmap::default();
+        let rect = Rect::new(0., 0., 160., 120.);
+        heatmap.add(rect, Vec2::new(5., 5.));
+        heatmap.add(rect, Vec2::new(9., 9.));
+        // outside of the area counts for the closest cell
+        heatmap.add(rect, Vec2::new(500., 500.));
+
+        assert_eq!(heatmap.max(), 2);
+        let cells: Vec<_> = heatmap.hot_cells(rect).collect();
+        assert_eq!(
+            cells,
+            vec![
+                (Vec2::new(5., 5.), Vec2::new(10., 10.), 2),
+                (Vec2::new(155., 115.), Vec2::new(10., 10.), 1),
+            ]
+        );
+        // twice the area after a resize, the kills scale along
+        let resized = Rect::new(0., 0., 320., 240.);
+        let cells: Vec<_> = heatmap.hot_cells(resized).collect();
+        assert_eq!(cells[0], (Vec2::new(10., 10.), Vec2::new(20., 20.), 2));
+    }
+}
@@ -0,0 +1,330 @@
+use std::collections::{BTreeMap, VecDeque};
+
+use bevy::asset::{Asset, Assets, Handle};
+use bevy::prelude::{Component, Entity, Event, EventWriter, Query, Res, Without};
+use bevy::reflect::TypePath;
+use bevy::sprite::TextureAtlas;
+use bevy::time::Time;
+use serde::Deserialize;
+
+use super::Bow;
+
+/// Frames are shown at least this long, so a clip with a 0 duration can't get stuck
+const MIN_FRAME_TIME: f32 = 0.001;
+
+/// Named clips of one sprite sheet
+#[derive(Asset, TypePath, Deserialize, Debug)]
+pub struct AnimationClips(pub BTreeMap<String, Clip>);
+
+/// What happens once the last frame of a clip was shown
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+pub enum PlayMode {
+    /// Starts over from the first frame
+    #[default]
+    Loop,
+    /// Stays on the last frame
+    Once,
+    /// Plays backwards to the first frame, then forwards again
+    PingPong,
+}
+
+/// A range of frames in a sprite sheet
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub struct Clip {
+    /// The folder of loose frames `first` and `last` count in, without one they index the whole atlas
+    #[serde(default)]
+    pub folder: Option<String>,
+    /// Index of the first frame in the atlas
+    pub first: usize,
+    /// Index of the last frame in the atlas, at least `first`
+    pub last: usize,
+    /// Seconds each frame is shown, the last duration counts for all frames after it
+    pub durations: Vec<f32>,
+    #[serde(default)]
+    pub mode: PlayMode,
+    /// Events sent when the frame at a 0-based position in the clip is shown
+    #[serde(default)]
+    pub events: Vec<(usize, String)>,
+}
+
+impl Clip {
+    fn len(&self) -> usize {
+        self.last.saturating_sub(self.first) + 1
+    }
+
+    fn duration(&self, position: usize) -> f32 {
+        self.durations
+            .get(position)
+            .or(self.durations.last())
+            .copied()
+            .unwrap_or(MIN_FRAME_TIME)
+            .max(MIN_FRAME_TIME)
+    }
+
+    fn events_at(&self, position: usize) -> impl Iterator<Item = &String> {
+        self.events
+            .iter()
+            .filter(move |(frame, _)| *frame == position)
+            .map(|(_, name)| name)
+    }
+}
+
+/// Sent when a clip shows a frame with an event on it
+#[derive(Event, Clone, PartialEq, Debug)]
+pub struct AnimationEvent {
+    pub entity: Entity,
+    pub name: String,
+}
+
+/// Plays the clips of `clips` on the `TextureAtlas` of its entity
+#[derive(Component, Debug)]
+pub struct Animator {
+    pub clips: Handle<AnimationClips>,
+    clip: String,
+    /// 0-based position of the shown frame in the clip
+    position: usize,
+    /// Seconds the shown frame has been shown
+    elapsed: f32,
+    /// A ping-pong clip is on its way back
+    backwards: bool,
+    /// A clip played once reached its last frame
+    finished: bool,
+    /// The events of the first frame still need to be sent
+    entered: bool,
+    /// Clips played one after the other once the current one is done
+    queue: VecDeque<String>,
+    /// Scales how fast the frames go by
+    pub speed: f32,
+}
+
+impl Animator {
+    pub fn new(clips: Handle<AnimationClips>, clip: &str) -> Self {
+        Animator {
+            clips,
+            clip: clip.into(),
+            position: 0,
+            elapsed: 0.,
+            backwards: false,
+            finished: false,
+            entered: true,
+            queue: VecDeque::new(),
+            speed: 1.,
+        }
+    }
+
+    pub fn with_speed(mut self, speed: f32) -> Self {
+        self.speed = speed;
+        self
+    }
+
+    /// The name of the playing clip
+    pub fn clip(&self) -> &str {
+        &self.clip
+    }
+
+    /// Starts `clip` from its first frame and drops the queue. Keeps playing if `clip` already is
+    pub fn play(&mut self, clip: &str) -> &mut Self {
+        if self.clip != clip {
+            self.start(clip.into());
+        }
+        self.queue.clear();
+        self
+    }
+
+    /// Plays `clip` once the current clip and everything queued before is done.
+    /// Looping clips are done at the end of a round
+    pub fn queue(&mut self, clip: &str) -> &mut Self {
+        self.queue.push_back(clip.into());
+        self
+    }
+
+    /// Switches to `clip` at the same position and time into the frame,
+    /// e.g. between two versions of the same motion
+    pub fn cross_switch(&mut self, clip: &str) -> &mut Self {
+        self.clip = clip.into();
+        self.queue.clear();
+        self
+    }
+
+    /// Shows the frame `progress` of the way through the clip, from 0 for the first to 1 for the last
+    pub fn seek(&mut self, clips: &AnimationClips, progress: f32) {
+        let Some(clip) = clips.0.get(&self.clip) else {
+            return;
+        };
+        let position = (progress.clamp(0., 1.) * clip.len() as f32) as usize;
+        self.position = position.min(clip.len() - 1);
+        self.elapsed = 0.;
+    }
+
+    /// The atlas index of the shown frame, `None` if the clip doesn't exist
+    pub fn frame(&self, clips: &AnimationClips) -> Option<usize> {
+        let clip = clips.0.get(&self.clip)?;
+        Some(clip.first + self.position.min(clip.len() - 1))
+    }
+
+    /// The folder the shown frame is in, if the clip is made of loose frames
+    pub fn folder<'a>(&self, clips: &'a AnimationClips) -> Option<&'a str> {
+        clips.0.get(&self.clip)?.folder.as_deref()
+    }
+
+    fn start(&mut self, clip: String) {
+        self.clip = clip;
+        self.position = 0;
+        self.elapsed = 0.;
+        self.backwards = false;
+        self.finished = false;
+        self.entered = true;
+    }
+
+    /// Moves one frame on, returns whether the clip got to its end
+    fn step(&mut self, clip: &Clip) -> bool {
+        let last = clip.len() - 1;
+        // a cross switch may have come from a longer clip
+        self.position = self.position.min(last);
+        match clip.mode {
+            PlayMode::Loop if self.position >= last => {
+                self.position = 0;
+                true
+            }
+            PlayMode::Once if self.position >= last => {
+                self.finished = true;
+                true
+            }
+            PlayMode::Loop | PlayMode::Once => {
+                self.position += 1;
+                false
+            }
+            PlayMode::PingPong if self.backwards || last == 0 => {
+                self.position = self.position.saturating_sub(1);
+                let back = self.position == 0;
+                self.backwards = !back;
+                back
+            }
+            PlayMode::PingPong => {
+                self.position += 1;
+                self.backwards = self.position >= last;
+                false
+            }
+        }
+    }
+
+    /// Lets `delta` seconds pass, pushes the events of every frame shown on the way to `events`
+    pub fn advance(&mut self, clips: &AnimationClips, delta: f32, events: &mut Vec<String>) {
+        self.elapsed += delta * self.speed;
+        loop {
+            if self.finished {
+                let Some(next) = self.queue.pop_front() else {
+                    return;
+                };
+                self.start(next);
+            }
+            let Some(clip) = clips.0.get(&self.clip) else {
+                return;
+            };
+            if self.entered {
+                self.entered = false;
+                events.extend(clip.events_at(self.position).cloned());
+            }
+            let duration = clip.duration(self.position);
+            if self.elapsed < duration {
+                return;
+            }
+            self.elapsed -= duration;
+            if self.step(clip) {
+                if let Some(next) = self.queue.pop_front() {
+                    let rest = self.elapsed;
+                    self.start(next);
+                    self.elapsed = rest;
+                    continue;
+                }
+                if self.finished {
+                    self.elapsed = 0.;
+                    return;
+                }
+            }
+            events.extend(clip.events_at(self.position).cloned());
+        }
+    }
+}
+
+/// Plays the animators of everything but the bow, `animate_bow` plays that one
+pub fn animate_sprites(
+    time: Res<Time>,
+    clips: Res<Assets<AnimationClips>>,
+    mut query: Query<(Entity, &mut Animator, &mut TextureAtlas), Without<Bow>>,
+    mut events: EventWriter<AnimationEvent>,
+) {
+    for (entity, mut animator, mut atlas) in &mut query {
+        let Some(library) = clips.get(&animator.clips) else {
+            continue;
+        };
+        let mut fired = Vec::new();
+        animator.advance(library, time.delta_seconds(), &mut fired);
+        events.send_batch(
+            fired
+                .into_iter()
+                .map(|name| AnimationEvent { entity, name }),
+        );
+        if let Some(index) = animator.frame(library) {
+            if atlas.index != index {
+                atlas.index = index;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_clips() -> AnimationClips {
+        ron::from_str(
+            r#"({
+                "loop": (first: 0, last: 2, durations: [0.1]),
+                "once": (first: 3, last: 4, durations: [0.1, 0.3], mode: Once),
+                "ping": (first: 5, last: 7, durations: [0.1], mode: PingPong, events: [(2, "top")]),
+            })"#,
+        )
+        .unwrap()
+    }
+
+    /// The frames an animator shows over `ticks` steps of 0.1 seconds
+    fn frames(animator: &mut Animator, ticks: usize, events: &mut Vec<String>) -> Vec<usize> {
+        let clips = test_clips();
+        (0..ticks)
+            .map(|_| {
+                animator.advance(&clips, 0.1, events);
+                animator.frame(&clips).unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn clips_loop_play_once_or_ping_pong() {
+        let mut events = Vec::new();
+        let mut looping = Animator::new(Handle::default(), "loop");
+        assert_eq!(frames(&mut looping, 4, &mut events), [1, 2, 0, 1]);
+
+        let mut once = Animator::new(Handle::default(), "once");
+        assert_eq!(frames(&mut once, 5, &mut events), [4, 4, 4, 4, 4]);
+
+        let mut ping = Animator::new(Handle::default(), "ping").with_speed(2.);
+        assert_eq!(frames(&mut ping, 3, &mut events), [7, 5, 7]);
+        assert_eq!(events, ["top", "top"]);
+    }
+
+    #[test]
+    fn queued_clips_wait_and_cross_switches_keep_the_frame() {
+        let mut animator = Animator::new(Handle::default(), "once");
+        animator.queue("loop");
+        let mut events = Vec::new();
+        // the last frame of "once" is shown for 0.3 seconds
+        assert_eq!(frames(&mut animator, 5, &mut events), [4, 4, 4, 0, 1]);
+        assert_eq!(animator.clip(), "loop");
+
+        animator.cross_switch("ping");
+        assert_eq!(animator.frame(&test_clips()), Some(6));
+        animator.play("once");
+        assert_eq!(animator.frame(&test_clips()), Some(3));
+    }
+}
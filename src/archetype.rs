@@ -0,0 +1,128 @@
+use std::ops::Range;
+
+use bevy::asset::Asset;
+use bevy::math::FloatExt;
+use bevy::reflect::TypePath;
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::movement::Movement;
+
+/// All the kinds of enemies that can be spawned
+#[derive(Asset, TypePath, Deserialize, Debug)]
+pub struct EnemyArchetypes(pub Vec<EnemyArchetype>);
+
+impl EnemyArchetypes {
+    /// Picks an archetype, archetypes with a higher `weight` are picked more often
+    pub fn choose(&self, rng: &mut impl Rng) -> Option<&EnemyArchetype> {
+        let weights = WeightedIndex::new(self.0.iter().map(|archetype| archetype.weight)).ok()?;
+        self.0.get(weights.sample(rng))
+    }
+
+    /// Picks an archetype by the names and weights in `mix`, names without an archetype
+    /// are left out. By `choose` if none of the names is an archetype
+    pub fn choose_from(
+        &self,
+        mix: &[(String, f32)],
+        rng: &mut impl Rng,
+    ) -> Option<&EnemyArchetype> {
+        let known: Vec<_> = mix
+            .iter()
+            .filter_map(|(name, weight)| {
+                let archetype = self.0.iter().find(|archetype| &archetype.name == name)?;
+                Some((archetype, *weight))
+            })
+            .collect();
+        if known.is_empty() {
+            return self.choose(rng);
+        }
+        let weights = WeightedIndex::new(known.iter().map(|(_, weight)| *weight)).ok()?;
+        Some(known[weights.sample(rng)].0)
+    }
+}
+
+/// One kind of enemy
+#[derive(Deserialize, Clone, Debug)]
+pub struct EnemyArchetype {
+    pub name: String,
+    /// How often this archetype gets picked compared to the others
+    pub weight: f32,
+    pub sprite_sheet: String,
+    pub frames: FrameLayout,
+    /// The `AnimationClips` of the sprite sheet
+    pub clips: String,
+    /// The clip played while flying
+    pub animation: String,
+    /// How much faster than in `clips` the animation plays
+    pub animation_speed: f32,
+    pub speed: Range<f32>,
+    /// Width and height of the sprite
+    pub size: Range<f32>,
+    pub health: f32,
+    /// Points for killing an enemy of this archetype
+    pub score: u32,
+    pub movement: Movement,
+}
+
+/// How the frames of a sprite sheet are laid out
+#[derive(Deserialize, Clone, Debug)]
+pub struct FrameLayout {
+    pub width: f32,
+    pub height: f32,
+    pub columns: usize,
+    pub rows: usize,
+}
+
+/// A random value in `range`, also for empty ranges
+pub fn random_in(range: &Range<f32>, rng: &mut impl Rng) -> f32 {
+    range.start.lerp(range.end, rng.gen::<f32>())
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::{thread_rng, SeedableRng};
+
+    use super::*;
+    use crate::animation::AnimationClips;
+
+    #[test]
+    fn shipped_archetypes_parse() {
+        let archetypes: EnemyArchetypes =
+            ron::from_str(include_str!("../assets/enemies.archetypes.ron")).unwrap();
+
+        assert!(archetypes.choose(&mut thread_rng()).is_some());
+    }
+
+    #[test]
+    fn shipped_enemy_clips_exist_and_stay_inside_their_sprite_sheets() {
+        let archetypes: EnemyArchetypes =
+            ron::from_str(include_str!("../assets/enemies.archetypes.ron")).unwrap();
+
+        for archetype in &archetypes.0 {
+            let clips = std::fs::read_to_string(format!("assets/{}", archetype.clips)).unwrap();
+            let clips: AnimationClips = ron::from_str(&clips).unwrap();
+            assert!(clips.0.contains_key(&archetype.animation));
+            let frames = archetype.frames.columns * archetype.frames.rows;
+            for (name, clip) in &clips.0 {
+                assert!(clip.last < frames, "{} {name}", archetype.name);
+            }
+        }
+    }
+
+    #[test]
+    fn archetype_mix_picks_by_name() {
+        let archetypes: EnemyArchetypes =
+            ron::de::from_str(include_str!("../assets/enemies.archetypes.ron")).unwrap();
+        let mut rng = StdRng::seed_from_u64(6);
+        let mix = vec![("Gull".to_string(), 1.), ("Missing".to_string(), 1.)];
+        for _ in 0..10 {
+            let archetype = archetypes.choose_from(&mix, &mut rng).unwrap();
+            assert_eq!(archetype.name, "Gull");
+        }
+        // a mix of only unknown names still spawns something
+        let missing = vec![("Missing".to_string(), 1.)];
+        assert!(archetypes.choose_from(&missing, &mut rng).is_some());
+    }
+}
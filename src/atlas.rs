@@ -0,0 +1,216 @@
+use std::cmp::Ordering;
+use std::path::Path;
+
+use bevy::asset::{
+    AssetId, AssetServer, Assets, Handle, LoadState, LoadedFolder, RecursiveDependencyLoadState,
+};
+use bevy::log::error;
+use bevy::math::Vec2;
+use bevy::render::texture::Image;
+use bevy::sprite::{
+    TextureAtlas, TextureAtlasBuilder, TextureAtlasBuilderError, TextureAtlasLayout,
+};
+
+/// A texture atlas packed at load time from the loose frames in some folders.
+/// Frames are found by their folder and their position in it, sorted by file name
+/// with numbers in the order they count, so `frame10.png` comes after `frame9.png`
+#[derive(Default)]
+pub struct FolderAtlas {
+    folders: Vec<(String, Handle<LoadedFolder>)>,
+    /// The frames of every folder once they are loaded, a frame that failed stays in its place
+    frames: Vec<(String, Vec<AssetId<Image>>)>,
+    /// Empty until the frames are packed
+    pub texture: Handle<Image>,
+    /// Empty until the frames are packed
+    pub layout: Handle<TextureAtlasLayout>,
+    done: bool,
+}
+
+impl FolderAtlas {
+    /// Starts loading the frames in `folders`, subfolders are left out
+    pub fn load(
+        asset_server: &AssetServer,
+        images: &Assets<Image>,
+        layouts: &Assets<TextureAtlasLayout>,
+        folders: &[&str],
+    ) -> Self {
+        FolderAtlas {
+            folders: folders
+                .iter()
+                .map(|folder| {
+                    (
+                        folder.to_string(),
+                        asset_server.load_folder(folder.to_string()),
+                    )
+                })
+                .collect(),
+            frames: Vec::new(),
+            texture: images.reserve_handle(),
+            layout: layouts.reserve_handle(),
+            done: false,
+        }
+    }
+
+    /// Whether the frames were packed or failed to load
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+
+    /// Whether not a single frame made it into the atlas, also until the frames are packed
+    pub fn is_empty(&self, layouts: &Assets<TextureAtlasLayout>) -> bool {
+        layouts
+            .get(&self.layout)
+            .is_none_or(|layout| layout.textures.is_empty())
+    }
+
+    /// The atlas index of the frame at 0-based `position` in `folder`,
+    /// `None` until the frames are packed or if that frame failed to load
+    pub fn index(
+        &self,
+        folder: &str,
+        position: usize,
+        layouts: &Assets<TextureAtlasLayout>,
+    ) -> Option<usize> {
+        let (_, frames) = self.frames.iter().find(|(name, _)| name == folder)?;
+        layouts
+            .get(&self.layout)?
+            .get_texture_index(*frames.get(position)?)
+    }
+
+    /// The images right in `folder`, sorted by file name
+    fn frames(folder: &str, loaded: &LoadedFolder) -> Vec<Handle<Image>> {
+        let mut frames: Vec<_> = loaded
+            .handles
+            .iter()
+            .filter(|handle| {
+                handle.path().and_then(|path| path.path().parent()) == Some(Path::new(folder))
+            })
+            .filter_map(|handle| handle.clone().try_typed::<Image>().ok())
+            .collect();
+        let name = |frame: &Handle<Image>| {
+            frame
+                .path()
+                .and_then(|path| path.path().file_name())
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default()
+        };
+        frames.sort_by(|a, b| natural_cmp(&name(a), &name(b)));
+        frames
+    }
+
+    /// Packs the frames into `texture` and `layout` once every one of them loaded or failed
+    pub fn pack(
+        &mut self,
+        asset_server: &AssetServer,
+        folders: &Assets<LoadedFolder>,
+        images: &mut Assets<Image>,
+        layouts: &mut Assets<TextureAtlasLayout>,
+    ) -> Result<(), TextureAtlasBuilderError> {
+        if self.done {
+            return Ok(());
+        }
+        let mut frames = Vec::new();
+        for (folder, handle) in &self.folders {
+            if asset_server.load_state(handle) == LoadState::Failed {
+                error!("could not load the frames in {folder}");
+                frames.push((folder.clone(), Vec::new()));
+                continue;
+            }
+            let Some(loaded) = folders.get(handle) else {
+                return Ok(());
+            };
+            let folder_frames = FolderAtlas::frames(folder, loaded);
+            match asset_server.recursive_dependency_load_state(handle) {
+                RecursiveDependencyLoadState::Loaded => {}
+                // the other frames may still be on their way
+                RecursiveDependencyLoadState::Failed
+                    if folder_frames.iter().all(|frame| {
+                        matches!(
+                            asset_server.load_state(frame),
+                            LoadState::Loaded | LoadState::Failed
+                        )
+                    }) => {}
+                _ => return Ok(()),
+            }
+            frames.push((folder.clone(), folder_frames));
+        }
+
+        let mut builder = TextureAtlasBuilder::default();
+        for frame in frames.iter().flat_map(|(_, frames)| frames) {
+            match images.get(frame) {
+                Some(image) => builder.add_texture(Some(frame.id()), image),
+                None => error!("could not load the frame {:?}", frame.path()),
+            }
+        }
+        self.done = true;
+        self.frames = frames
+            .into_iter()
+            .map(|(folder, frames)| (folder, frames.iter().map(Handle::id).collect()))
+            .collect();
+        let (layout, texture) = builder.finish()?;
+        images.insert(&self.texture, texture);
+        layouts.insert(&self.layout, layout);
+        Ok(())
+    }
+}
+
+/// Compares file names with the numbers in them by value, `bow2.png` before `bow10.png`
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    natural_key(a).cmp(&natural_key(b))
+}
+
+/// The text and numbers of `name` in turns. A number is keyed by its length without
+/// leading zeros first, so longer numbers are larger however many digits they have
+fn natural_key(name: &str) -> Vec<(usize, &str)> {
+    let mut key = Vec::new();
+    let mut rest = name;
+    while let Some(first) = rest.chars().next() {
+        let digits = first.is_ascii_digit();
+        let end = rest
+            .find(|c: char| c.is_ascii_digit() != digits)
+            .unwrap_or(rest.len());
+        let (chunk, tail) = rest.split_at(end);
+        if digits {
+            let number = chunk.trim_start_matches('0');
+            key.push((number.len(), number));
+        } else {
+            // text sorts after any number
+            key.push((usize::MAX, chunk));
+        }
+        rest = tail;
+    }
+    key
+}
+
+/// The size of the frame `atlas` shows, zero until its layout is there
+pub fn frame_size(atlas: &TextureAtlas, layouts: &Assets<TextureAtlasLayout>) -> Vec2 {
+    layouts
+        .get(&atlas.layout)
+        .and_then(|layout| layout.textures.get(atlas.index))
+        .map_or(Vec2::ZERO, |rect| rect.size())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames_sort_by_their_numbers() {
+        let mut names = vec![
+            "bow relese10.png",
+            "bow relese2.png",
+            "bow relese1.png",
+            "bow relese02b.png",
+        ];
+        names.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(
+            names,
+            [
+                "bow relese1.png",
+                "bow relese2.png",
+                "bow relese02b.png",
+                "bow relese10.png",
+            ]
+        );
+    }
+}
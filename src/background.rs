@@ -0,0 +1,207 @@
+use std::ops::Range;
+
+use bevy::asset::{Asset, AssetServer, Assets, Handle, LoadState};
+use bevy::math::{Rect, Vec2};
+use bevy::prelude::{
+    default, Commands, Component, Deref, Local, Query, Res, ResMut, Resource, With,
+};
+use bevy::reflect::TypePath;
+use bevy::render::color::Color;
+use bevy::render::texture::Image;
+use bevy::sprite::{Sprite, SpriteSheetBundle, TextureAtlas, TextureAtlasLayout};
+use bevy::time::Time;
+use bevy::transform::components::Transform;
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::archetype::random_in;
+use crate::rng::{GameRng, Seed};
+
+/// Mixed into the game's seed for the clouds, so they don't draw the same numbers as the game
+const CLOUD_SEED: u64 = 0x636c_6f75_6473;
+
+/// The clouds drifting behind, and maybe in front of, everything else
+#[derive(Asset, TypePath, Deserialize, Debug)]
+pub struct Background {
+    pub sheet: String,
+    /// The clouds on the sheet as pixel rectangles, (left, top, right, bottom)
+    pub clouds: Vec<(f32, f32, f32, f32)>,
+    /// Drawn in order, the far layers first
+    pub layers: Vec<CloudLayer>,
+}
+
+/// Clouds at the same depth, they all drift at the same speed
+#[derive(Deserialize, Clone, Debug)]
+pub struct CloudLayer {
+    pub count: usize,
+    /// Pixels per second the clouds drift, negative to the left
+    pub speed: f32,
+    /// Drawing order, the enemies and the bow are at 0. Above 0 the clouds cover the enemies
+    pub depth: f32,
+    pub scale: f32,
+    /// How opaque the clouds are
+    pub alpha: f32,
+    /// Where the clouds drift, from -1 at the bottom of the window to 1 at the top
+    pub height: Range<f32>,
+}
+
+#[derive(Resource, Deref)]
+pub struct BackgroundHandle(pub Handle<Background>);
+
+/// The size of the window the clouds drift through
+#[derive(Resource, Deref, Default)]
+pub struct Sky(Vec2);
+
+impl Sky {
+    pub fn new(size: Vec2) -> Self {
+        Sky(size)
+    }
+
+    /// Resizes the sky, the clouds move along so they stay spread over all of it
+    pub fn resize(&mut self, size: Vec2, clouds: &mut Query<&mut Transform, With<Cloud>>) {
+        if self.0 == size {
+            return;
+        }
+        let stretch = size / self.0.max(Vec2::ONE);
+        for mut tr in clouds.iter_mut() {
+            tr.translation.x *= stretch.x;
+            tr.translation.y *= stretch.y;
+        }
+        self.0 = size;
+    }
+}
+
+#[derive(Component)]
+pub struct Cloud {
+    speed: f32,
+    /// How far past the edge of the sky the cloud goes before it comes back on the other side
+    half_width: f32,
+}
+
+/// `x` moved back inside `-edge..edge` from the other side, like the sky is a cylinder
+fn wrap(x: f32, edge: f32) -> f32 {
+    if edge <= 0. {
+        return x;
+    }
+    (x + edge).rem_euclid(2. * edge) - edge
+}
+
+/// Spawns the cloud layers once the background and its sheet are loaded
+#[allow(clippy::too_many_arguments)]
+pub fn spawn_clouds(
+    mut commands: Commands,
+    mut spawned: Local<bool>,
+    mut sheet: Local<Option<Handle<Image>>>,
+    seed: Res<Seed>,
+    handle: Res<BackgroundHandle>,
+    backgrounds: Res<Assets<Background>>,
+    sky: Res<Sky>,
+    asset_server: Res<AssetServer>,
+    images: Res<Assets<Image>>,
+    mut layouts: ResMut<Assets<TextureAtlasLayout>>,
+) {
+    if *spawned {
+        return;
+    }
+    let Some(background) = backgrounds.get(&**handle) else {
+        return;
+    };
+    let texture = sheet
+        .get_or_insert_with(|| asset_server.load(&background.sheet))
+        .clone();
+    if asset_server.load_state(&texture) == LoadState::Failed {
+        *spawned = true;
+        return;
+    }
+    let Some(image) = images.get(&texture) else {
+        return;
+    };
+    *spawned = true;
+    if background.clouds.is_empty() {
+        return;
+    }
+    let mut layout = TextureAtlasLayout::new_empty(image.size_f32());
+    for &(left, top, right, bottom) in &background.clouds {
+        layout.add_texture(Rect::new(left, top, right, bottom));
+    }
+    let sizes: Vec<_> = layout.textures.iter().map(Rect::size).collect();
+    let layout = layouts.add(layout);
+
+    // clouds can hide the birds, so they are seeded like the rest of the game,
+    // but from an rng of their own so loading them doesn't change the gameplay draws
+    let mut rng = GameRng::new(Seed(seed.0 ^ CLOUD_SEED));
+    let rng = &mut *rng;
+    for layer in &background.layers {
+        for _ in 0..layer.count {
+            let index = rng.gen_range(0..sizes.len());
+            let x = rng.gen_range(-0.5..0.5) * sky.x;
+            let y = random_in(&layer.height, rng) * sky.y / 2.;
+            commands.spawn((
+                SpriteSheetBundle {
+                    texture: texture.clone(),
+                    atlas: TextureAtlas {
+                        layout: layout.clone(),
+                        index,
+                    },
+                    sprite: Sprite {
+                        color: Color::WHITE.with_a(layer.alpha),
+                        flip_x: rng.gen(),
+                        ..default()
+                    },
+                    transform: Transform::from_xyz(x, y, layer.depth)
+                        .with_scale(Vec2::splat(layer.scale).extend(1.)),
+                    ..default()
+                },
+                Cloud {
+                    speed: layer.speed,
+                    half_width: sizes[index].x * layer.scale / 2.,
+                },
+            ));
+        }
+    }
+}
+
+pub fn drift_clouds(time: Res<Time>, sky: Res<Sky>, mut clouds: Query<(&Cloud, &mut Transform)>) {
+    for (cloud, mut tr) in &mut clouds {
+        let x = tr.translation.x + cloud.speed * time.delta_seconds();
+        tr.translation.x = wrap(x, sky.x / 2. + cloud.half_width);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::render::render_asset::RenderAssetUsages;
+    use bevy::render::texture::{CompressedImageFormats, ImageSampler, ImageType};
+
+    use super::*;
+
+    #[test]
+    fn shipped_background_parses() {
+        let background: Background =
+            ron::from_str(include_str!("../assets/default.background.ron")).unwrap();
+
+        let sheet = Image::from_buffer(
+            &std::fs::read(format!("assets/{}", background.sheet)).unwrap(),
+            ImageType::Extension("png"),
+            CompressedImageFormats::NONE,
+            true,
+            ImageSampler::Default,
+            RenderAssetUsages::default(),
+        )
+        .unwrap();
+        let size = sheet.size_f32();
+
+        assert!(!background.layers.is_empty());
+        for &(left, top, right, bottom) in &background.clouds {
+            assert!(left < right && right <= size.x);
+            assert!(top < bottom && bottom <= size.y);
+        }
+    }
+
+    #[test]
+    fn clouds_wrap_around_the_sky() {
+        assert_eq!(wrap(90., 100.), 90.);
+        assert_eq!(wrap(110., 100.), -90.);
+        assert_eq!(wrap(-130., 100.), 70.);
+    }
+}
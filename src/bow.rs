@@ -0,0 +1,246 @@
+use bevy::asset::{AssetServer, Assets, LoadedFolder};
+use bevy::prelude::{Component, Deref, Entity, EventWriter, Query, Res, ResMut, Resource, With};
+use bevy::render::texture::Image;
+use bevy::sprite::{TextureAtlas, TextureAtlasLayout};
+use bevy::time::Time;
+
+use crate::animation::{AnimationClips, AnimationEvent, Animator};
+use crate::atlas::FolderAtlas;
+
+use super::{Bow, BowPullTime, DrawButton, Fixed, BOW_FULL_PULL_TIME};
+
+/// Seconds after the release before the bow can be drawn again
+const RECOVER_TIME: f32 = 0.25;
+/// The folders of bow frames the clips in `bow.clips.ron` are made of
+pub const FRAME_FOLDERS: [&str; 4] = [
+    "bow/bow pull",
+    "bow/bow pull/bow pull with arrow",
+    "bow/bow relese",
+    "bow/bow relese/bow relese with arrow",
+];
+
+/// All frames of the bow, packed into one atlas once they are loaded
+#[derive(Resource, Deref, Default)]
+pub struct BowAtlas(FolderAtlas);
+
+impl BowAtlas {
+    pub fn load(
+        asset_server: &AssetServer,
+        images: &Assets<Image>,
+        layouts: &Assets<TextureAtlasLayout>,
+    ) -> Self {
+        BowAtlas(FolderAtlas::load(
+            asset_server,
+            images,
+            layouts,
+            &FRAME_FOLDERS,
+        ))
+    }
+}
+
+pub fn bow_atlas_pending(atlas: Res<BowAtlas>) -> bool {
+    !atlas.is_done()
+}
+
+pub fn pack_bow_atlas(
+    asset_server: Res<AssetServer>,
+    folders: Res<Assets<LoadedFolder>>,
+    mut images: ResMut<Assets<Image>>,
+    mut layouts: ResMut<Assets<TextureAtlasLayout>>,
+    mut atlas: ResMut<BowAtlas>,
+) {
+    if let Err(err) = atlas
+        .0
+        .pack(&asset_server, &folders, &mut images, &mut layouts)
+    {
+        bevy::log::error!("could not pack the bow frames: {err}");
+    }
+    // the atlas is packed only once, so this is only said once
+    if atlas.is_done() && atlas.is_empty(&layouts) {
+        bevy::log::error!("none of the bow frames could be loaded, the bow won't be drawn");
+    }
+}
+
+/// What the bow is doing, decides the frame it shows
+#[derive(Component, Default, Clone, Copy, PartialEq, Debug)]
+pub enum BowState {
+    #[default]
+    Idle,
+    /// Being drawn, the frame follows `BowPullTime`
+    Pulling,
+    /// Fully drawn
+    Held,
+    /// The string springs back for as long as the release clip plays,
+    /// `arrow` if the draw was cancelled instead of shot
+    Releasing { arrow: bool },
+    /// Back at rest, but not ready to be drawn again yet
+    Recovering { elapsed: f32 },
+}
+
+impl BowState {
+    /// Whether the bow may be drawn, not while it is still releasing or recovering
+    pub fn can_draw(&self) -> bool {
+        !matches!(
+            self,
+            BowState::Releasing { .. } | BowState::Recovering { .. }
+        )
+    }
+
+    /// The state after `delta` seconds, `released` once the release clip played to its end
+    pub fn next(
+        self,
+        fixed: bool,
+        pull_time: f32,
+        cancelled: bool,
+        released: bool,
+        delta: f32,
+    ) -> BowState {
+        match self {
+            BowState::Idle | BowState::Pulling | BowState::Held if fixed => {
+                if pull_time >= BOW_FULL_PULL_TIME {
+                    BowState::Held
+                } else {
+                    BowState::Pulling
+                }
+            }
+            BowState::Idle => BowState::Idle,
+            BowState::Pulling | BowState::Held => BowState::Releasing { arrow: cancelled },
+            BowState::Releasing { .. } if released => BowState::Recovering { elapsed: 0. },
+            BowState::Releasing { arrow } => BowState::Releasing { arrow },
+            BowState::Recovering { elapsed } => {
+                let elapsed = elapsed + delta;
+                if elapsed >= RECOVER_TIME {
+                    BowState::Idle
+                } else {
+                    BowState::Recovering { elapsed }
+                }
+            }
+        }
+    }
+}
+
+/// Moves the bow through its states and plays the matching clip,
+/// the bow is left out of `animate_sprites` so its animator only moves here
+#[allow(clippy::type_complexity)]
+pub fn animate_bow(
+    time: Res<Time>,
+    clips: Res<Assets<AnimationClips>>,
+    bow_atlas: Res<BowAtlas>,
+    layouts: Res<Assets<TextureAtlasLayout>>,
+    button: Res<DrawButton>,
+    mut query: Query<
+        (
+            Entity,
+            &Fixed,
+            &BowPullTime,
+            &mut BowState,
+            &mut Animator,
+            &mut TextureAtlas,
+        ),
+        With<Bow>,
+    >,
+    mut events: EventWriter<AnimationEvent>,
+) {
+    for (entity, fixed, pull_time, mut state, mut animator, mut atlas) in &mut query {
+        let library = clips.get(&animator.clips);
+        // the release clip went on to the queued idle one, or there is no clip to wait for
+        let released = library
+            .is_none_or(|library| animator.clip() == "idle" || animator.frame(library).is_none());
+        let previous = *state;
+        *state = previous.next(
+            **fixed,
+            **pull_time,
+            button.cancelled(),
+            released,
+            time.delta_seconds(),
+        );
+        match *state {
+            BowState::Idle | BowState::Recovering { .. } => {
+                animator.play("idle");
+            }
+            BowState::Pulling => {
+                animator.play("pull");
+            }
+            // trembles on from the last frame of the pull
+            BowState::Held if animator.clip() != "held" => {
+                animator.cross_switch("held");
+            }
+            BowState::Releasing { arrow, .. } if previous.can_draw() => {
+                let clip = if arrow {
+                    "release_with_arrow"
+                } else {
+                    "release"
+                };
+                animator.play(clip).queue("idle");
+            }
+            _ => {}
+        }
+        let Some(library) = library else {
+            continue;
+        };
+        if *state == BowState::Pulling {
+            animator.seek(library, **pull_time / BOW_FULL_PULL_TIME);
+        } else {
+            let mut fired = Vec::new();
+            animator.advance(library, time.delta_seconds(), &mut fired);
+            events.send_batch(
+                fired
+                    .into_iter()
+                    .map(|name| AnimationEvent { entity, name }),
+            );
+        }
+        // only touch the index when it changes, so the sprite isn't rebuilt every tick
+        let index = animator
+            .frame(library)
+            .and_then(|frame| match animator.folder(library) {
+                Some(folder) => bow_atlas.index(folder, frame, &layouts),
+                None => Some(frame),
+            });
+        if let Some(index) = index {
+            if atlas.index != index {
+                atlas.index = index;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shipped_clips_parse() {
+        let bow: AnimationClips =
+            ron::from_str(include_str!("../assets/bow/bow.clips.ron")).unwrap();
+        for clip in ["idle", "pull", "held", "release", "release_with_arrow"] {
+            assert!(bow.0.contains_key(clip), "{clip}");
+        }
+        // every clip stays inside the frames of a folder packed into the bow atlas
+        for (name, clip) in &bow.0 {
+            let folder = clip.folder.as_deref().unwrap();
+            assert!(FRAME_FOLDERS.contains(&folder), "{name}");
+            let frames = std::fs::read_dir(format!("assets/{folder}"))
+                .unwrap()
+                .filter(|entry| entry.as_ref().unwrap().path().extension() == Some("png".as_ref()))
+                .count();
+            assert!(clip.last < frames, "{name}");
+        }
+    }
+
+    #[test]
+    fn bow_goes_through_its_states() {
+        let tick = 1. / 64.;
+        let state = BowState::Idle.next(true, 0.1, false, false, tick);
+        assert_eq!(state, BowState::Pulling);
+        let state = state.next(true, BOW_FULL_PULL_TIME, false, false, tick);
+        assert_eq!(state, BowState::Held);
+        let state = state.next(false, 0., true, false, tick);
+        assert_eq!(state, BowState::Releasing { arrow: true });
+        assert!(!state.can_draw());
+        // releasing takes as long as the clip, not a set time
+        assert_eq!(state.next(false, 0., false, false, 1.), state);
+        let state = state.next(false, 0., false, true, tick);
+        assert_eq!(state, BowState::Recovering { elapsed: 0. });
+        assert_eq!(state.next(false, 0., false, false, 1.), BowState::Idle);
+    }
+}
@@ -0,0 +1,61 @@
+use std::path::PathBuf;
+
+/// The command line arguments without the program name
+pub fn args() -> Vec<String> {
+    std::env::args().skip(1).collect()
+}
+
+/// The value of `<name> <value>` or `<name>=<value>`
+pub fn arg_value(args: &[String], name: &str) -> Option<String> {
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == name {
+            return args.next().cloned();
+        }
+        if let Some(value) = arg
+            .strip_prefix(name)
+            .and_then(|rest| rest.strip_prefix('='))
+        {
+            return Some(value.to_string());
+        }
+    }
+    None
+}
+
+/// Where settings and stats are kept: `--data-dir <dir>` or the platform's user data directory
+pub fn data_dir(args: &[String]) -> Option<PathBuf> {
+    arg_value(args, "--data-dir")
+        .map(PathBuf::from)
+        .or_else(|| user_data_dir().map(|dir| dir.join("bevy-bow")))
+}
+
+fn user_data_dir() -> Option<PathBuf> {
+    let var = |name| std::env::var_os(name).map(PathBuf::from);
+    if cfg!(windows) {
+        var("APPDATA")
+    } else if cfg!(target_os = "macos") {
+        var("HOME").map(|home| home.join("Library/Application Support"))
+    } else {
+        var("XDG_DATA_HOME").or_else(|| var("HOME").map(|home| home.join(".local/share")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seed_is_read_from_the_command_line() {
+        let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            arg_value(&args(&["--seed", "42"]), "--seed"),
+            Some("42".to_string())
+        );
+        assert_eq!(
+            arg_value(&args(&["--other", "--seed=7"]), "--seed"),
+            Some("7".to_string())
+        );
+        assert_eq!(arg_value(&args(&["--seed"]), "--seed"), None);
+        assert_eq!(arg_value(&args(&["--seeds=3"]), "--seed"), None);
+    }
+}
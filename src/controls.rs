@@ -0,0 +1,110 @@
+use bevy::input::{keyboard::KeyCode, ButtonInput};
+use bevy::math::Vec2;
+use bevy::prelude::{Component, Query, Reflect, Res, ResMut, Resource, With};
+use bevy::text::Text;
+use serde::{Deserialize, Serialize};
+
+/// Key in the menu that switches between the control schemes
+const CONTROLS_KEY: KeyCode = KeyCode::KeyC;
+
+/// How the bow is moved and aimed
+#[derive(Resource, Reflect, Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+pub enum ControlScheme {
+    /// The bow follows the cursor until it is drawn, then the cursor aims it
+    #[default]
+    Cursor,
+    /// Keys or a stick move the bow inside `BowArea` while the cursor aims it all the time
+    Twin {
+        /// Pixels per second the bow moves
+        speed: f32,
+        /// How fast a drawn bow can move at most, as fast as ever if `None`
+        draw_speed: Option<f32>,
+    },
+}
+
+impl ControlScheme {
+    pub const TWIN: ControlScheme = ControlScheme::Twin {
+        speed: 300.,
+        draw_speed: Some(100.),
+    };
+
+    /// Pixels per second the bow moves with keys and sticks, `None` if the cursor moves it
+    pub fn bow_speed(&self, drawing: bool) -> Option<f32> {
+        match *self {
+            ControlScheme::Cursor => None,
+            ControlScheme::Twin { speed, draw_speed } if drawing => {
+                Some(draw_speed.map_or(speed, |limit| limit.min(speed)))
+            }
+            ControlScheme::Twin { speed, .. } => Some(speed),
+        }
+    }
+
+    /// The way a bow at `bow` shoots with the cursor at `cursor`, zero with the cursor right on it.
+    /// The cursor pulls the bow back, so it shoots away from it, unless the cursor aims it
+    pub fn aim(&self, bow: Vec2, cursor: Vec2) -> Vec2 {
+        match self {
+            ControlScheme::Cursor => (bow - cursor).normalize_or_zero(),
+            ControlScheme::Twin { .. } => (cursor - bow).normalize_or_zero(),
+        }
+    }
+
+    /// Whether the bow turns towards the aim even when it isn't drawn
+    pub fn aims_freely(&self) -> bool {
+        matches!(self, ControlScheme::Twin { .. })
+    }
+
+    fn describe(&self) -> &'static str {
+        match self {
+            ControlScheme::Cursor => "the cursor moves the bow",
+            ControlScheme::Twin { .. } => "WASD or the left stick move the bow",
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct ControlsUi;
+
+pub fn toggle_controls(keys: Res<ButtonInput<KeyCode>>, mut controls: ResMut<ControlScheme>) {
+    if keys.just_pressed(CONTROLS_KEY) {
+        *controls = match *controls {
+            ControlScheme::Cursor => ControlScheme::TWIN,
+            ControlScheme::Twin { .. } => ControlScheme::Cursor,
+        };
+    }
+}
+
+pub fn update_controls_ui(
+    controls: Res<ControlScheme>,
+    mut query: Query<&mut Text, With<ControlsUi>>,
+) {
+    for mut text in &mut query {
+        text.sections[0].value =
+            format!("Controls: {} (press {CONTROLS_KEY:?})", controls.describe());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn draw_speed_limit_is_optional() {
+        let unlimited = ControlScheme::Twin {
+            speed: 300.,
+            draw_speed: None,
+        };
+        assert_eq!(unlimited.bow_speed(true), Some(300.));
+        assert_eq!(ControlScheme::TWIN.bow_speed(true), Some(100.));
+        assert_eq!(ControlScheme::TWIN.bow_speed(false), Some(300.));
+        assert_eq!(ControlScheme::default().bow_speed(false), None);
+    }
+
+    #[test]
+    fn twin_controls_shoot_towards_the_cursor() {
+        let bow = Vec2::new(-200., 0.);
+        let cursor = Vec2::new(-200., 100.);
+        assert_eq!(ControlScheme::Cursor.aim(bow, cursor), Vec2::NEG_Y);
+        assert_eq!(ControlScheme::TWIN.aim(bow, cursor), Vec2::Y);
+        assert_eq!(ControlScheme::TWIN.aim(bow, bow), Vec2::ZERO);
+    }
+}
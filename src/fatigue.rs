@@ -0,0 +1,169 @@
+use bevy::input::{keyboard::KeyCode, ButtonInput};
+use bevy::prelude::{Component, Query, Reflect, Res, ResMut, Resource, With};
+use bevy::text::Text;
+use bevy::time::Time;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use super::{Bow, BowPullTime, Fixed, BOW_FULL_PULL_TIME};
+
+/// How fast a tired bow shakes back and forth, in radians per second
+const SHAKE_FREQUENCY: f32 = 25.;
+/// Key in the menu that turns fatigue on and off
+const FATIGUE_KEY: KeyCode = KeyCode::KeyT;
+
+/// An optional rule: holding a fully drawn bow for too long makes it shake and shoot less accurately
+#[derive(Resource, Reflect, Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub struct Fatigue {
+    pub enabled: bool,
+    /// Seconds a fully drawn bow can be held before it starts to shake
+    pub after: f32,
+    /// Seconds from the first shake to full fatigue
+    pub ramp: f32,
+    /// How far the bow turns back and forth at full fatigue, in radians
+    pub shake: f32,
+    /// How far a shot may stray at full fatigue, in radians
+    pub spread: f32,
+}
+
+impl Default for Fatigue {
+    fn default() -> Self {
+        Fatigue {
+            enabled: false,
+            after: 2.,
+            ramp: 3.,
+            shake: 0.08,
+            spread: 0.25,
+        }
+    }
+}
+
+/// How tired the arms holding the bow are
+#[derive(Component, Default)]
+pub struct Tired {
+    /// Seconds the bow has been held fully drawn
+    held: f32,
+    /// 0 when fresh, 1 at full fatigue
+    pub level: f32,
+}
+
+impl Tired {
+    /// The angle the shaking turns the bow by right now
+    pub fn shake(&self, fatigue: &Fatigue) -> f32 {
+        self.level * fatigue.shake * (self.held * SHAKE_FREQUENCY).sin()
+    }
+
+    /// A random angle a shot strays by. Nothing is drawn from `rng` while the bow is fresh,
+    /// so recordings made without fatigue play out the same
+    pub fn spread(&self, fatigue: &Fatigue, rng: &mut impl Rng) -> f32 {
+        if self.level <= 0. {
+            return 0.;
+        }
+        rng.gen_range(-1.0..=1.0) * self.level * fatigue.spread
+    }
+}
+
+pub fn tire_bow(
+    time: Res<Time>,
+    fatigue: Res<Fatigue>,
+    mut bow: Query<(&Fixed, &BowPullTime, &mut Tired), With<Bow>>,
+) {
+    for (fixed, pull_time, mut tired) in &mut bow {
+        if !fatigue.enabled || !**fixed || **pull_time < BOW_FULL_PULL_TIME {
+            *tired = Tired::default();
+            continue;
+        }
+        tired.held += time.delta_seconds();
+        tired.level = ((tired.held - fatigue.after) / fatigue.ramp).clamp(0., 1.);
+    }
+}
+
+#[derive(Component)]
+pub struct FatigueUi;
+
+pub fn toggle_fatigue(keys: Res<ButtonInput<KeyCode>>, mut fatigue: ResMut<Fatigue>) {
+    if keys.just_pressed(FATIGUE_KEY) {
+        fatigue.enabled = !fatigue.enabled;
+    }
+}
+
+pub fn update_fatigue_ui(fatigue: Res<Fatigue>, mut query: Query<&mut Text, With<FatigueUi>>) {
+    let state = if fatigue.enabled { "on" } else { "off" };
+    for mut text in &mut query {
+        text.sections[0].value = format!("Fatigue: {state} (press {FATIGUE_KEY:?})");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::app::{App, Update};
+    use bevy::prelude::default;
+    use bevy::utils::Duration;
+
+    use super::*;
+    use crate::rng::{GameRng, Seed};
+    use crate::tests::test_app;
+
+    fn fatigue_app(enabled: bool) -> App {
+        let mut app = test_app(Duration::from_millis(250));
+        app.insert_resource(Fatigue {
+            enabled,
+            after: 1.,
+            ramp: 1.,
+            ..default()
+        })
+        .add_systems(Update, tire_bow);
+        app.world.spawn((
+            Bow,
+            Fixed(true),
+            BowPullTime(BOW_FULL_PULL_TIME),
+            Tired::default(),
+        ));
+        app
+    }
+
+    fn tiredness(app: &mut App) -> f32 {
+        let mut bow = app.world.query::<&Tired>();
+        bow.single(&app.world).level
+    }
+
+    #[test]
+    fn holding_a_full_draw_tires_the_bow() {
+        let mut app = fatigue_app(true);
+        for _ in 0..4 {
+            app.update();
+        }
+        assert_eq!(tiredness(&mut app), 0.);
+        for _ in 0..4 {
+            app.update();
+        }
+        assert_eq!(tiredness(&mut app), 1.);
+
+        let fatigue = *app.world.resource::<Fatigue>();
+        let mut bow = app.world.query::<&Tired>();
+        let tired = bow.single(&app.world);
+        assert!(tired.shake(&fatigue).abs() <= fatigue.shake);
+        let mut rng = GameRng::new(Seed(1));
+        let spreads: Vec<f32> = (0..10).map(|_| tired.spread(&fatigue, &mut *rng)).collect();
+        assert!(spreads.iter().all(|spread| spread.abs() <= fatigue.spread));
+        assert!(spreads.iter().any(|spread| *spread != 0.));
+
+        // lowering the bow rests the arms right away
+        app.world
+            .query::<&mut super::Fixed>()
+            .single_mut(&mut app.world)
+            .0 = false;
+        app.update();
+        assert_eq!(tiredness(&mut app), 0.);
+    }
+
+    #[test]
+    fn fatigue_is_off_by_default() {
+        assert!(!Fatigue::default().enabled);
+        let mut app = fatigue_app(false);
+        for _ in 0..20 {
+            app.update();
+        }
+        assert_eq!(tiredness(&mut app), 0.);
+    }
+}
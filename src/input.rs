@@ -0,0 +1,669 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use bevy::ecs::system::SystemParam;
+use bevy::input::gamepad::{
+    Gamepad, GamepadAxis, GamepadAxisType, GamepadButton, GamepadButtonType, Gamepads,
+};
+use bevy::input::{keyboard::KeyCode, mouse::MouseButton, Axis, ButtonInput};
+use bevy::log::{error, info, warn};
+use bevy::math::Vec2;
+use bevy::prelude::{
+    Commands, Component, DetectChanges, Local, Query, Res, ResMut, Resource, With,
+};
+use bevy::text::Text;
+use bevy::utils::HashSet;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::cli::data_dir;
+
+const BINDINGS_FILE: &str = "bindings.ron";
+/// How far a trigger or stick has to be pushed to count as pressed
+const AXIS_THRESHOLD: f32 = 0.5;
+/// Stick movement below this is ignored when aiming
+const STICK_DEAD_ZONE: f32 = 0.15;
+
+/// What the player can do with a button, whatever device it is on
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum Action {
+    /// Draws the bow while held, letting go shoots
+    Draw,
+    /// Shoots without letting go of `Draw`
+    Release,
+    /// Lowers the bow without shooting
+    Cancel,
+    /// Switches to the next arrow type
+    CycleArrow,
+}
+
+impl Action {
+    pub const ALL: [Action; 4] = [
+        Action::Draw,
+        Action::Release,
+        Action::Cancel,
+        Action::CycleArrow,
+    ];
+}
+
+/// A button on any device
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum Binding {
+    Mouse(MouseButton),
+    Key(KeyCode),
+    Gamepad(GamepadButtonType),
+    /// A trigger or stick axis pushed past `AXIS_THRESHOLD`, `true` for the negative direction
+    GamepadAxis(GamepadAxisType, bool),
+}
+
+/// Where a binding is pushed, an action keeps one binding on each
+#[derive(PartialEq)]
+enum Device {
+    Mouse,
+    Keyboard,
+    Gamepad,
+}
+
+impl Binding {
+    fn device(&self) -> Device {
+        match self {
+            Binding::Mouse(_) => Device::Mouse,
+            Binding::Key(_) => Device::Keyboard,
+            Binding::Gamepad(_) | Binding::GamepadAxis(..) => Device::Gamepad,
+        }
+    }
+}
+
+/// What pushes the aim or the bow in some direction
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum DirectionBinding {
+    /// The aim follows the cursor whenever it moves, doesn't move the bow
+    Mouse,
+    /// Pushes in the direction of the held keys
+    Keys {
+        up: KeyCode,
+        down: KeyCode,
+        left: KeyCode,
+        right: KeyCode,
+    },
+    /// Pushes with the stick, harder the further it is pushed
+    GamepadStick {
+        x: GamepadAxisType,
+        y: GamepadAxisType,
+    },
+}
+
+/// Which buttons trigger which action and what moves the aim and the bow, saved next to the stats
+#[derive(Resource, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(default)]
+pub struct Bindings {
+    pub actions: BTreeMap<Action, Vec<Binding>>,
+    pub aim: Vec<DirectionBinding>,
+    /// Only used by the twin stick controls, see `ControlScheme`
+    pub movement: Vec<DirectionBinding>,
+    /// Pixels per second the aim moves with keys and sticks
+    pub aim_speed: f32,
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        Bindings {
+            actions: BTreeMap::from([
+                (
+                    Action::Draw,
+                    vec![
+                        Binding::Mouse(MouseButton::Left),
+                        Binding::Key(KeyCode::Space),
+                        Binding::Gamepad(GamepadButtonType::RightTrigger2),
+                    ],
+                ),
+                (
+                    Action::Release,
+                    vec![
+                        Binding::Key(KeyCode::KeyF),
+                        Binding::Gamepad(GamepadButtonType::RightTrigger),
+                    ],
+                ),
+                (
+                    Action::Cancel,
+                    vec![
+                        Binding::Mouse(MouseButton::Right),
+                        Binding::Key(KeyCode::KeyX),
+                        Binding::Gamepad(GamepadButtonType::East),
+                    ],
+                ),
+                (
+                    Action::CycleArrow,
+                    vec![
+                        Binding::Mouse(MouseButton::Middle),
+                        Binding::Key(KeyCode::Tab),
+                        Binding::Gamepad(GamepadButtonType::North),
+                    ],
+                ),
+            ]),
+            aim: vec![
+                DirectionBinding::Mouse,
+                DirectionBinding::Keys {
+                    up: KeyCode::ArrowUp,
+                    down: KeyCode::ArrowDown,
+                    left: KeyCode::ArrowLeft,
+                    right: KeyCode::ArrowRight,
+                },
+                DirectionBinding::GamepadStick {
+                    x: GamepadAxisType::RightStickX,
+                    y: GamepadAxisType::RightStickY,
+                },
+            ],
+            movement: vec![
+                DirectionBinding::Keys {
+                    up: KeyCode::KeyW,
+                    down: KeyCode::KeyS,
+                    left: KeyCode::KeyA,
+                    right: KeyCode::KeyD,
+                },
+                DirectionBinding::GamepadStick {
+                    x: GamepadAxisType::LeftStickX,
+                    y: GamepadAxisType::LeftStickY,
+                },
+            ],
+            aim_speed: 600.,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum BindingsError {
+    #[error("could not read or write the bindings: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("could not parse the bindings: {0}")]
+    Parse(#[from] ron::error::SpannedError),
+    #[error("could not serialize the bindings: {0}")]
+    Serialize(#[from] ron::Error),
+}
+
+impl Bindings {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, BindingsError> {
+        let bytes = std::fs::read(path)?;
+        Ok(ron::de::from_bytes(&bytes)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), BindingsError> {
+        if let Some(dir) = path.as_ref().parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let text = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        std::fs::write(path, text)?;
+        Ok(())
+    }
+
+    pub fn get(&self, action: Action) -> &[Binding] {
+        self.actions.get(&action).map_or(&[], Vec::as_slice)
+    }
+
+    /// Makes `binding` the only one on its device for `action`,
+    /// so a new key replaces the old key but the mouse and gamepad buttons stay
+    pub fn rebind(&mut self, action: Action, binding: Binding) {
+        let bindings = self.actions.entry(action).or_default();
+        bindings.retain(|old| old.device() != binding.device());
+        bindings.push(binding);
+    }
+}
+
+#[derive(Resource, Clone, Debug, Default)]
+pub struct BindingsPath(pub Option<PathBuf>);
+
+impl BindingsPath {
+    pub fn from_args(args: &[String]) -> Self {
+        BindingsPath(data_dir(args).map(|dir| dir.join(BINDINGS_FILE)))
+    }
+}
+
+/// Reads the bindings, or writes the defaults so there is a file to edit
+pub fn load_bindings(path: Res<BindingsPath>, mut bindings: ResMut<Bindings>) {
+    let Some(path) = &path.0 else {
+        return;
+    };
+    if !path.exists() {
+        if let Err(err) = bindings.save(path) {
+            error!("{err}");
+        }
+        return;
+    }
+    match Bindings::load(path) {
+        Ok(loaded) => {
+            *bindings = loaded;
+            info!("loaded bindings from {}", path.display());
+        }
+        // the file is left alone so a typo can be fixed without losing everything else
+        Err(err) => warn!("{err}, using the default bindings"),
+    }
+}
+
+/// Every input device in one place
+#[derive(SystemParam)]
+pub struct Devices<'w> {
+    keys: Res<'w, ButtonInput<KeyCode>>,
+    mouse: Res<'w, ButtonInput<MouseButton>>,
+    gamepads: Res<'w, Gamepads>,
+    buttons: Res<'w, ButtonInput<GamepadButton>>,
+    axes: Res<'w, Axis<GamepadAxis>>,
+}
+
+impl Devices<'_> {
+    fn axis(&self, gamepad: Gamepad, axis_type: GamepadAxisType) -> f32 {
+        self.axes
+            .get(GamepadAxis::new(gamepad, axis_type))
+            .unwrap_or(0.)
+    }
+
+    fn pressed(&self, binding: Binding) -> bool {
+        match binding {
+            Binding::Mouse(button) => self.mouse.pressed(button),
+            Binding::Key(key) => self.keys.pressed(key),
+            Binding::Gamepad(button_type) => self.gamepads.iter().any(|gamepad| {
+                self.buttons
+                    .pressed(GamepadButton::new(gamepad, button_type))
+            }),
+            Binding::GamepadAxis(axis_type, negative) => self.gamepads.iter().any(|gamepad| {
+                let value = self.axis(gamepad, axis_type);
+                if negative {
+                    value <= -AXIS_THRESHOLD
+                } else {
+                    value >= AXIS_THRESHOLD
+                }
+            }),
+        }
+    }
+
+    /// Which way and how hard `binding` is pushed, at most 1 long
+    fn direction(&self, binding: DirectionBinding) -> Vec2 {
+        match binding {
+            // the cursor sets the aim directly
+            DirectionBinding::Mouse => Vec2::ZERO,
+            DirectionBinding::Keys {
+                up,
+                down,
+                left,
+                right,
+            } => {
+                let axis = |negative, positive| {
+                    f32::from(u8::from(self.keys.pressed(positive)))
+                        - f32::from(u8::from(self.keys.pressed(negative)))
+                };
+                Vec2::new(axis(left, right), axis(down, up)).normalize_or_zero()
+            }
+            DirectionBinding::GamepadStick { x, y } => self
+                .gamepads
+                .iter()
+                .map(|gamepad| Vec2::new(self.axis(gamepad, x), self.axis(gamepad, y)))
+                .find(|stick| stick.length() > STICK_DEAD_ZONE)
+                .unwrap_or(Vec2::ZERO)
+                .clamp_length_max(1.),
+        }
+    }
+
+    /// The first button that went down this frame, on any device
+    fn just_pressed(&self) -> Option<Binding> {
+        let key = self
+            .keys
+            .get_just_pressed()
+            .next()
+            .copied()
+            .map(Binding::Key);
+        let mouse = || {
+            self.mouse
+                .get_just_pressed()
+                .next()
+                .copied()
+                .map(Binding::Mouse)
+        };
+        let gamepad = || {
+            self.buttons
+                .get_just_pressed()
+                .next()
+                .map(|button| Binding::Gamepad(button.button_type))
+        };
+        key.or_else(mouse).or_else(gamepad)
+    }
+
+    /// Every axis pushed past `AXIS_THRESHOLD` right now, on any gamepad
+    fn pushed_axes(&self) -> Vec<Binding> {
+        self.axes
+            .devices()
+            .filter_map(|axis| {
+                let value = self.axes.get(*axis)?;
+                (value.abs() >= AXIS_THRESHOLD)
+                    .then_some(Binding::GamepadAxis(axis.axis_type, value < 0.))
+            })
+            .collect()
+    }
+
+    fn any_pressed(&self) -> bool {
+        self.keys.get_pressed().next().is_some()
+            || self.mouse.get_pressed().next().is_some()
+            || self.buttons.get_pressed().next().is_some()
+            || !self.pushed_axes().is_empty()
+    }
+}
+
+/// The actions of this frame, read from whatever device is bound to them
+#[derive(Resource, Default, Debug)]
+pub struct ActionState {
+    pressed: HashSet<Action>,
+    previous: HashSet<Action>,
+    /// The bow was released or cancelled and stays lowered until `Draw` is let go
+    lowered: Option<Action>,
+    /// Where keys and sticks push the aim
+    pub aim: Vec2,
+    /// Where keys and sticks push the bow
+    pub movement: Vec2,
+}
+
+impl ActionState {
+    pub fn pressed(&self, action: Action) -> bool {
+        self.pressed.contains(&action)
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.pressed(action) && !self.previous.contains(&action)
+    }
+
+    /// Whether the bow is being drawn
+    pub fn drawing(&self) -> bool {
+        self.pressed(Action::Draw) && self.lowered.is_none()
+    }
+
+    /// Whether the draw was cancelled, the bow is lowered without shooting
+    pub fn cancelled(&self) -> bool {
+        self.lowered == Some(Action::Cancel)
+    }
+
+    fn update(&mut self, pressed: HashSet<Action>, aim: Vec2, movement: Vec2) {
+        self.previous = std::mem::replace(&mut self.pressed, pressed);
+        if !self.pressed(Action::Draw) {
+            self.lowered = None;
+        } else if self.lowered.is_none() {
+            // cancelling wins if both are pressed at once
+            self.lowered = [Action::Cancel, Action::Release]
+                .into_iter()
+                .find(|action| self.just_pressed(*action));
+        }
+        self.aim = aim;
+        self.movement = movement;
+    }
+}
+
+pub fn read_actions(
+    devices: Devices,
+    bindings: Res<Bindings>,
+    rebinding: Option<Res<Rebinding>>,
+    mut actions: ResMut<ActionState>,
+) {
+    // buttons pressed to rebind don't do anything else
+    if rebinding.is_some() {
+        actions.update(HashSet::new(), Vec2::ZERO, Vec2::ZERO);
+        return;
+    }
+    let pressed = Action::ALL
+        .into_iter()
+        .filter(|action| {
+            bindings
+                .get(*action)
+                .iter()
+                .any(|binding| devices.pressed(*binding))
+        })
+        .collect();
+    let direction = |bindings: &[DirectionBinding]| {
+        bindings
+            .iter()
+            .map(|binding| devices.direction(*binding))
+            .sum::<Vec2>()
+            .clamp_length_max(1.)
+    };
+    actions.update(
+        pressed,
+        direction(&bindings.aim),
+        direction(&bindings.movement),
+    );
+}
+
+/// Going through the actions in the menu, the next button pressed or trigger or stick pushed
+/// gets bound to the current one. What moves the aim and the bow is only in `bindings.ron`
+#[derive(Resource, Default)]
+pub struct Rebinding(usize);
+
+#[derive(Component)]
+pub struct RebindingUi;
+
+/// Key in the menu that starts rebinding
+const REBIND_KEY: KeyCode = KeyCode::KeyR;
+
+pub fn start_rebinding(
+    mut commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    rebinding: Option<Res<Rebinding>>,
+) {
+    if rebinding.is_none() && keys.just_pressed(REBIND_KEY) {
+        commands.insert_resource(Rebinding::default());
+    }
+}
+
+pub fn capture_binding(
+    mut commands: Commands,
+    devices: Devices,
+    path: Res<BindingsPath>,
+    mut rebinding: ResMut<Rebinding>,
+    mut bindings: ResMut<Bindings>,
+    mut pushed: Local<Vec<Binding>>,
+) {
+    // an axis counts when it gets pushed, not while it stays pushed like a resting trigger
+    let axes = devices.pushed_axes();
+    let axis = axes.iter().copied().find(|axis| !pushed.contains(axis));
+    *pushed = axes;
+    if rebinding.is_added() {
+        return;
+    }
+    let Some(&action) = Action::ALL.get(rebinding.0) else {
+        // wait for the last button to be let go, or it would start the game
+        if !devices.any_pressed() {
+            commands.remove_resource::<Rebinding>();
+            if let Some(path) = &path.0 {
+                if let Err(err) = bindings.save(path) {
+                    error!("{err}");
+                }
+            }
+        }
+        return;
+    };
+    let Some(binding) = devices.just_pressed().or(axis) else {
+        return;
+    };
+    // escape keeps what is there
+    if binding != Binding::Key(KeyCode::Escape) {
+        bindings.rebind(action, binding);
+    }
+    rebinding.0 += 1;
+}
+
+pub fn update_rebinding_ui(
+    rebinding: Option<Res<Rebinding>>,
+    bindings: Res<Bindings>,
+    mut query: Query<&mut Text, With<RebindingUi>>,
+) {
+    let line = match rebinding.and_then(|rebinding| Action::ALL.get(rebinding.0).copied()) {
+        Some(action) => format!(
+            "Press a button for {action:?}, Esc keeps {:?}",
+            bindings.get(action)
+        ),
+        None => format!("Press {REBIND_KEY:?} to change the controls"),
+    };
+    for mut text in &mut query {
+        text.sections[0].value.clone_from(&line);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::app::{App, Update};
+
+    use super::*;
+
+    #[test]
+    fn bindings_survive_a_round_trip() {
+        let dir = std::env::temp_dir().join(format!("bevy-bow-bindings-{}", std::process::id()));
+        let path = dir.join("bindings.ron");
+        let mut bindings = Bindings::default();
+        bindings.rebind(Action::Draw, Binding::Key(KeyCode::KeyJ));
+        bindings.aim_speed = 300.;
+
+        bindings.save(&path).unwrap();
+
+        assert_eq!(Bindings::load(&path).unwrap(), bindings);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn rebinding_keeps_the_other_devices() {
+        let mut bindings = Bindings::default();
+        bindings.rebind(Action::Draw, Binding::Key(KeyCode::KeyJ));
+
+        assert_eq!(
+            bindings.get(Action::Draw),
+            [
+                Binding::Mouse(MouseButton::Left),
+                Binding::Gamepad(GamepadButtonType::RightTrigger2),
+                Binding::Key(KeyCode::KeyJ),
+            ]
+        );
+
+        bindings.rebind(
+            Action::Draw,
+            Binding::GamepadAxis(GamepadAxisType::RightZ, false),
+        );
+        bindings.rebind(Action::Draw, Binding::Mouse(MouseButton::Right));
+        assert_eq!(
+            bindings.get(Action::Draw),
+            [
+                Binding::Key(KeyCode::KeyJ),
+                Binding::GamepadAxis(GamepadAxisType::RightZ, false),
+                Binding::Mouse(MouseButton::Right),
+            ]
+        );
+    }
+
+    fn input_app() -> App {
+        let mut app = App::new();
+        app.init_resource::<ButtonInput<KeyCode>>()
+            .init_resource::<ButtonInput<MouseButton>>()
+            .init_resource::<Gamepads>()
+            .init_resource::<ButtonInput<GamepadButton>>()
+            .init_resource::<Axis<GamepadAxis>>()
+            .init_resource::<Bindings>()
+            .init_resource::<ActionState>()
+            .add_systems(Update, read_actions);
+        app
+    }
+
+    fn hold(app: &mut App, key: KeyCode, pressed: bool) {
+        let mut keys = app.world.resource_mut::<ButtonInput<KeyCode>>();
+        if pressed {
+            keys.press(key);
+        } else {
+            keys.release(key);
+        }
+        app.update();
+    }
+
+    #[test]
+    fn release_shoots_without_letting_go_of_draw() {
+        let mut app = input_app();
+        let drawing = |app: &App| app.world.resource::<ActionState>().drawing();
+
+        hold(&mut app, KeyCode::Space, true);
+        assert!(drawing(&app));
+        hold(&mut app, KeyCode::KeyF, true);
+        assert!(!drawing(&app));
+        hold(&mut app, KeyCode::KeyF, false);
+        assert!(!drawing(&app));
+        // drawing again takes a fresh press
+        hold(&mut app, KeyCode::Space, false);
+        hold(&mut app, KeyCode::Space, true);
+        assert!(drawing(&app));
+    }
+
+    #[test]
+    fn aim_keys_push_the_aim() {
+        let mut app = input_app();
+
+        hold(&mut app, KeyCode::ArrowRight, true);
+        hold(&mut app, KeyCode::ArrowUp, true);
+
+        let aim = app.world.resource::<ActionState>().aim;
+        assert!((aim - Vec2::ONE.normalize()).length() < 1e-6);
+        assert_eq!(app.world.resource::<ActionState>().movement, Vec2::ZERO);
+    }
+
+    #[test]
+    fn movement_keys_push_the_bow() {
+        let mut app = input_app();
+
+        hold(&mut app, KeyCode::KeyA, true);
+
+        let actions = app.world.resource::<ActionState>();
+        assert_eq!(actions.movement, Vec2::NEG_X);
+        assert_eq!(actions.aim, Vec2::ZERO);
+    }
+
+    #[test]
+    fn cancel_lowers_the_bow_until_draw_is_let_go() {
+        let mut app = input_app();
+        let state = |app: &App| {
+            let actions = app.world.resource::<ActionState>();
+            (actions.drawing(), actions.cancelled())
+        };
+
+        hold(&mut app, KeyCode::Space, true);
+        assert_eq!(state(&app), (true, false));
+        hold(&mut app, KeyCode::KeyX, true);
+        assert_eq!(state(&app), (false, true));
+        hold(&mut app, KeyCode::KeyX, false);
+        assert_eq!(state(&app), (false, true));
+        hold(&mut app, KeyCode::Space, false);
+        assert_eq!(state(&app), (false, false));
+    }
+
+    #[test]
+    fn pushed_triggers_and_sticks_can_be_bound() {
+        let mut app = input_app();
+        app.init_resource::<BindingsPath>()
+            .insert_resource(Rebinding::default())
+            .add_systems(Update, capture_binding);
+        let push = |app: &mut App, axis_type, value| {
+            app.world
+                .resource_mut::<Axis<GamepadAxis>>()
+                .set(GamepadAxis::new(Gamepad::new(0), axis_type), value);
+            app.update();
+        };
+
+        // already pushed when the rebinding started
+        push(&mut app, GamepadAxisType::LeftZ, 1.);
+        push(&mut app, GamepadAxisType::RightZ, 1.);
+        assert_eq!(app.world.resource::<Rebinding>().0, 1);
+        push(&mut app, GamepadAxisType::LeftStickY, -0.8);
+        assert_eq!(app.world.resource::<Rebinding>().0, 2);
+
+        let bindings = app.world.resource::<Bindings>();
+        assert_eq!(
+            bindings.get(Action::Draw),
+            [
+                Binding::Mouse(MouseButton::Left),
+                Binding::Key(KeyCode::Space),
+                Binding::GamepadAxis(GamepadAxisType::RightZ, false),
+            ]
+        );
+        assert_eq!(
+            bindings.get(Action::Release)[1],
+            Binding::GamepadAxis(GamepadAxisType::LeftStickY, true)
+        );
+    }
+}
@@ -1,12 +1,15 @@
 use bevy::app::prelude::Plugin;
 use bevy::asset::{load_internal_asset, prelude::Assets, Asset, Handle};
-use bevy::ecs::prelude::{Bundle, Component, Query, ResMut};
+use bevy::ecs::prelude::{Bundle, Commands, Component, Entity, Query, ResMut, Without};
+use bevy::math::Vec2;
 use bevy::reflect::TypePath;
 use bevy::render::{
+    camera::Camera,
     prelude::Color,
     render_resource::{AsBindGroup, Shader},
 };
-use bevy::ui::{node_bundles::MaterialNodeBundle, Style, UiMaterial, UiMaterialPlugin};
+use bevy::transform::components::GlobalTransform;
+use bevy::ui::{node_bundles::MaterialNodeBundle, Node, Style, UiMaterial, UiMaterialPlugin, Val};
 use bevy::utils::default;
 
 pub const PROGRESS_BAR_HANDLE: Handle<Shader> =
@@ -21,7 +24,7 @@ impl Plugin for ProgressBarPlugin {
             "progress_shader.wgsl",
             Shader::from_wgsl
         );
-        app.add_systems(bevy::app::Update, update_progress_bar)
+        app.add_systems(bevy::app::Update, (update_progress_bar, follow_anchor))
             .add_plugins(UiMaterialPlugin::<ProgressBarMaterial>::default());
     }
 }
@@ -46,8 +49,8 @@ impl ProgressBar {
     ///
     /// # Examples
     /// ```
-    /// use bevy_progressbar::ProgressBar;
-    /// use bevy_render::prelude::Color;
+    /// use bevy_bow::ProgressBar;
+    /// use bevy::render::prelude::Color;
     /// let bar = ProgressBar::new(vec![(10, Color::RED), (9, Color::BLUE)]);
     /// ```
     pub fn new(sections: Vec<(u32, Color)>) -> Self {
@@ -75,7 +78,7 @@ impl ProgressBar {
     /// # Examples
     ///
     /// ```
-    /// use bevy_progressbar::ProgressBar;
+    /// use bevy_bow::ProgressBar;
     ///
     /// let mut bar = ProgressBar::default();
     /// bar.set_progress(0.5);
@@ -98,7 +101,7 @@ impl ProgressBar {
     ///
     /// # Examples
     /// ```
-    /// use bevy_progressbar::ProgressBar;
+    /// use bevy_bow::ProgressBar;
     /// let mut bar = ProgressBar::default();
     /// bar.increase_progress(0.5);
     /// assert_eq!(bar.get_progress(), 0.5);
@@ -121,7 +124,7 @@ impl ProgressBar {
     ///
     /// # Examples
     /// ```
-    /// use bevy_progressbar::ProgressBar;
+    /// use bevy_bow::ProgressBar;
     /// let mut bar = ProgressBar::default();
     /// assert_eq!(bar.is_finished(), false);
     /// bar.increase_progress(1.0);
@@ -152,6 +155,17 @@ impl Default for ProgressBar {
     }
 }
 
+/// Keeps a ProgressBar centered above an entity in the world
+/// instead of at a fixed spot in the ui.
+/// The bar is despawned once the entity is gone
+#[derive(Component)]
+pub struct ProgressBarAnchor {
+    /// The entity the bar follows
+    pub target: Entity,
+    /// The world space offset from the target to the top of the bar
+    pub offset: Vec2,
+}
+
 #[derive(Bundle)]
 pub struct ProgressBarBundle {
     progressbar: ProgressBar,
@@ -241,3 +255,28 @@ fn update_progress_bar(
         material.update(bar);
     }
 }
+
+fn follow_anchor(
+    mut commands: Commands,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    targets: Query<&GlobalTransform, Without<ProgressBarAnchor>>,
+    mut bars: Query<(Entity, &ProgressBarAnchor, &Node, &mut Style)>,
+) {
+    let Some((camera, camera_transform)) = cameras.iter().find(|(camera, _)| camera.is_active)
+    else {
+        return;
+    };
+
+    for (bar, anchor, node, mut style) in &mut bars {
+        let Ok(target) = targets.get(anchor.target) else {
+            commands.entity(bar).despawn();
+            continue;
+        };
+
+        let world = target.translation() + anchor.offset.extend(0.);
+        if let Some(viewport) = camera.world_to_viewport(camera_transform, world) {
+            style.left = Val::Px(viewport.x - node.size().x / 2.);
+            style.top = Val::Px(viewport.y);
+        }
+    }
+}
@@ -1,18 +1,31 @@
+use analytics::{
+    collect_analytics, draw_heatmap, spawn_analytics_ui, toggle_analytics_overlay,
+    update_analytics_ui, Analytics, AnalyticsOverlay,
+};
+use animation::{animate_sprites, AnimationClips, AnimationEvent, Animator};
+use archetype::{random_in, EnemyArchetypes};
+use background::{drift_clouds, spawn_clouds, Background, BackgroundHandle, Cloud, Sky};
 use bevy::{
-    app::{App, FixedUpdate, Startup, Update},
-    asset::{AssetServer, Assets},
+    app::{App, FixedUpdate, Last, PreUpdate, Startup, Update},
+    asset::{AssetApp, AssetServer, Assets, Handle},
     ecs::reflect,
+    ecs::schedule::{
+        common_conditions::{in_state, not, resource_changed, resource_exists},
+        Condition, OnEnter, OnExit,
+    },
     hierarchy::BuildChildren,
-    input::ButtonInput,
+    input::{keyboard::KeyCode, mouse::MouseWheel, ButtonInput, InputSystem},
     math::{FloatExt, Quat, Rect, Vec2, Vec2Swizzles, Vec3, Vec3Swizzles},
     prelude::{
-        default, Camera2dBundle, Changed, Circle, Commands, Component, Deref, DerefMut, Entity,
-        Event, EventReader, EventWriter, Gizmos, IntoSystemConfigs, Line2d, MouseButton, Query,
-        ReflectResource, Res, ResMut, Resource, With,
+        default, Camera2dBundle, Changed, Circle, Commands, Component, Deref, DerefMut,
+        DetectChanges, DetectChangesMut, Entity, EntityWorldMut, Event, EventReader, EventWriter,
+        Gizmos, IntoSystemConfigs, IntoSystemSetConfigs, Line2d, Local, MouseButton, Name, Query,
+        ReflectResource, Res, ResMut, Resource, SystemSet, With, Without,
     },
     reflect::{FromReflect, Reflect},
     render::{
         camera::Camera, color::Color, mesh::Mesh, render_resource::encase::rts_array::Length,
+        texture::Image,
     },
     sprite::{
         ColorMaterial, MaterialMesh2dBundle, Sprite, SpriteBundle, SpriteSheetBundle, TextureAtlas,
@@ -22,16 +35,67 @@ use bevy::{
     time::{Time, Timer, TimerMode},
     transform::components::{GlobalTransform, Transform},
     ui::{node_bundles::TextBundle, PositionType, Style, Val},
-    window::{PrimaryWindow, Window},
+    utils::{HashMap, HashSet},
+    window::{CursorMoved, PrimaryWindow, Window},
     DefaultPlugins,
 };
-use bevy_bow::{ProgressBar, ProgressBarBundle, ProgressBarMaterial, ProgressBarPlugin};
+use bevy_bow::{
+    ProgressBar, ProgressBarAnchor, ProgressBarBundle, ProgressBarMaterial, ProgressBarPlugin,
+};
 use bevy_editor_pls::EditorPlugin;
 use bevy_inspector_egui::quick::{ResourceInspectorPlugin, WorldInspectorPlugin};
+use bow::{animate_bow, bow_atlas_pending, pack_bow_atlas, BowAtlas, BowState};
+use controls::{toggle_controls, update_controls_ui, ControlScheme};
+use fatigue::{tire_bow, toggle_fatigue, update_fatigue_ui, Fatigue, Tired};
+use input::{
+    capture_binding, load_bindings, read_actions, start_rebinding, update_rebinding_ui, Action,
+    ActionState, Bindings, BindingsPath, DirectionBinding, Rebinding,
+};
+use mode::{
+    choose_mode, escape_enemies, refills_quiver, track_run, update_mode_choice, update_mode_ui,
+    EnemyEscapedEvent, GameMode, ModeUi, Run,
+};
+use movement::{apply_patterns, flock, follow_path, Anchor, FlightTime, FlockId, Movement};
+use path_finding::{
+    AvoidLineOfFire, LongDiagonals, MaxAngleChange, MinLength, PathContext, PathFinder,
+    PathFindingStrategy,
+};
+use pull::{draw_string, pull_bow, toggle_pull_mode, update_pull_mode_ui, DragAnchor, PullMode};
 use rand::prelude::*;
+use replay::{record_input, replay_input, save_recording, Recorder, Recording, Replay};
+use rng::{log_seed, GameRng, Seed};
+use ron_asset::RonAssetLoader;
+use score::{float_popups, is_headshot, score_kills, Combo};
+use state::{
+    despawn_all, navigate, show_game_over, show_menu, show_pause, skip_menu, GameOverUi, GameState,
+    InGame, MenuUi, PauseUi,
+};
+use stats::{load_stats, record_game, NewHighScore, Stats, StatsPath};
+use wave::{check_waves, run_waves, SpawnEnemyEvent, WavePhase, WaveState, Waves, WavesHandle};
+
+mod analytics;
+mod animation;
+mod archetype;
+mod atlas;
+mod background;
+mod bow;
+mod cli;
+mod controls;
+mod fatigue;
+mod input;
+mod mode;
+mod movement;
+mod path_finding;
+mod pull;
+mod replay;
+mod rng;
+mod ron_asset;
+mod score;
+mod state;
+mod stats;
+mod wave;
 
 const BOW_FULL_PULL_TIME: f32 = 1.;
-const BOW_SIZE: f32 = 190. / 3.;
 
 const SCOREBOARD_FONT_SIZE: f32 = 20.0;
 const SCOREBOARD_TEXT_PADDING: Val = Val::Px(5.0);
@@ -39,53 +103,305 @@ const SCOREBOARD_TEXT_PADDING: Val = Val::Px(5.0);
 const TEXT_COLOR: Color = Color::rgb(0.5, 0.5, 1.0);
 const SCORE_COLOR: Color = Color::rgb(1.0, 0.5, 0.5);
 
+/// The speed at which an arrow deals exactly its `Damage`, faster arrows deal more
+const FULL_DAMAGE_SPEED: f32 = 1000.;
+const KNOCKBACK_SPEED: f32 = 300.;
+/// How fast a knockback slows down, per second
+const KNOCKBACK_DAMPING: f32 = 8.;
+const HIT_FLASH_TIME: f32 = 0.15;
+const HIT_FLASH_COLOR: Color = Color::rgb(1.0, 0.3, 0.3);
+
+const QUIVER_CAPACITY: u32 = 10;
+const QUIVER_RELOAD_TIME: f32 = 1.5;
+/// How long the quiver display flashes after trying to shoot with an empty quiver
+const DRY_FIRE_FLASH_TIME: f32 = 0.3;
+
+const PICKUP_DROP_CHANCE: f32 = 0.3;
+const PICKUP_ARROWS: u32 = 2;
+const PICKUP_SPEED: f32 = 400.;
+/// Pickups this close to the bow are collected
+const PICKUP_RADIUS: f32 = 32.;
+/// Arrows lying on the ground, the oldest ones disappear once there are more
+const MAX_LANDED_ARROWS: usize = 30;
+/// Seconds an arrow that landed out of the bow's reach lies there before it flies back
+const LANDED_HOMING_DELAY: f32 = 1.;
+
 fn main() {
-    App::new()
-        .add_plugins(DefaultPlugins)
+    let args = cli::args();
+    let replay = cli::arg_value(&args, "--replay").map(|path| match Recording::load(&path) {
+        Ok(recording) => Replay::new(recording),
+        Err(err) => {
+            eprintln!("could not replay {path}: {err}");
+            std::process::exit(1);
+        }
+    });
+    let seed = replay
+        .as_ref()
+        .map_or_else(|| Seed::from_args(&args), Replay::seed);
+    let mode = replay.as_ref().map_or_else(GameMode::default, Replay::mode);
+    let fatigue = replay
+        .as_ref()
+        .map_or_else(Fatigue::default, Replay::fatigue);
+    let controls = replay
+        .as_ref()
+        .map_or_else(ControlScheme::default, Replay::controls);
+    let pull_mode = replay
+        .as_ref()
+        .map_or_else(PullMode::default, Replay::pull_mode);
+
+    let mut app = App::new();
+    app.add_plugins(DefaultPlugins)
         .add_plugins(ProgressBarPlugin)
         .add_plugins(WorldInspectorPlugin::new())
+        .insert_resource(seed)
+        .insert_resource(GameRng::new(seed))
+        .insert_resource(StatsPath::from_args(&args))
+        .insert_resource(BindingsPath::from_args(&args))
+        .init_resource::<Bindings>()
+        .init_resource::<ActionState>()
+        .init_resource::<Stats>()
+        .init_resource::<NewHighScore>()
+        .init_resource::<Analytics>()
+        .init_resource::<AnalyticsOverlay>()
         .insert_resource(Mouse(Vec2::ZERO))
+        .init_resource::<MoveInput>()
+        .init_resource::<DrawButton>()
         .insert_resource(Scoreboard(0))
+        .init_resource::<Combo>()
+        .insert_resource(mode)
+        .insert_resource(fatigue)
+        .insert_resource(controls)
+        .insert_resource(pull_mode)
+        .init_resource::<Run>()
         .insert_resource(G(18.))
-        .insert_resource(SpawnTimer(Timer::from_seconds(2., TimerMode::Repeating)))
+        .init_resource::<WaveState>()
+        .init_resource::<ArrowCatalog>()
+        .insert_resource(SelectedArrow(0))
+        .insert_resource(Quiver::new(QUIVER_CAPACITY, QUIVER_RELOAD_TIME))
+        .init_asset::<EnemyArchetypes>()
+        .register_asset_loader(RonAssetLoader::<EnemyArchetypes>::new(&["archetypes.ron"]))
+        .init_asset::<Waves>()
+        .register_asset_loader(RonAssetLoader::<Waves>::new(&["waves.ron"]))
+        .init_asset::<AnimationClips>()
+        .register_asset_loader(RonAssetLoader::<AnimationClips>::new(&["clips.ron"]))
+        .init_asset::<Background>()
+        .register_asset_loader(RonAssetLoader::<Background>::new(&["background.ron"]))
+        .register_type::<WavePhase>()
+        .register_type::<GameMode>()
+        .register_type::<Fatigue>()
+        .register_type::<ControlScheme>()
+        .register_type::<PullMode>()
+        .register_type::<Option<f32>>()
+        .register_type::<ArrowType>()
+        .register_type::<ArrowKind>()
+        .register_type::<Option<Split>>()
+        .register_type::<PathFinder>()
+        .register_type::<MinLength>()
+        .register_type::<MaxAngleChange>()
+        .register_type::<AvoidLineOfFire>()
+        .register_type::<LongDiagonals>()
         .add_plugins(ResourceInspectorPlugin::<G>::new())
-        .add_plugins(ResourceInspectorPlugin::<SpawnTimer>::new())
-        .add_systems(Startup, (setup).chain())
+        .add_plugins(ResourceInspectorPlugin::<GameMode>::new())
+        .add_plugins(ResourceInspectorPlugin::<Fatigue>::new())
+        .add_plugins(ResourceInspectorPlugin::<ControlScheme>::new())
+        .add_plugins(ResourceInspectorPlugin::<PullMode>::new())
+        .add_plugins(ResourceInspectorPlugin::<WaveState>::new())
+        .add_plugins(ResourceInspectorPlugin::<ArrowCatalog>::new())
+        .add_plugins(ResourceInspectorPlugin::<Quiver>::new())
+        .add_plugins(ResourceInspectorPlugin::<PathFinder>::new())
+        .init_state::<GameState>()
+        .add_systems(
+            Startup,
+            (
+                setup,
+                log_seed,
+                load_stats,
+                load_bindings,
+                spawn_analytics_ui,
+            )
+                .chain(),
+        )
+        .add_systems(OnEnter(GameState::Menu), show_menu)
+        .add_systems(OnExit(GameState::Menu), (despawn_all::<MenuUi>, start_game))
+        .add_systems(OnEnter(GameState::Paused), show_pause)
+        .add_systems(OnExit(GameState::Paused), despawn_all::<PauseUi>)
+        .add_systems(
+            OnEnter(GameState::GameOver),
+            // watching a replay doesn't count as playing
+            (
+                record_game.run_if(not(resource_exists::<Replay>)),
+                show_game_over,
+            )
+                .chain(),
+        )
+        .add_systems(
+            OnExit(GameState::GameOver),
+            (despawn_all::<GameOverUi>, despawn_all::<InGame>),
+        )
+        .add_systems(
+            Update,
+            (
+                navigate.run_if(
+                    not(resource_exists::<Replay>).and_then(not(resource_exists::<Rebinding>)),
+                ),
+                skip_menu.run_if(resource_exists::<Replay>.and_then(in_state(GameState::Menu))),
+            ),
+        )
+        .add_systems(
+            Update,
+            (toggle_analytics_overlay, update_analytics_ui, draw_heatmap),
+        )
+        .add_systems(Update, pack_bow_atlas.run_if(bow_atlas_pending))
+        .add_systems(Update, check_waves)
+        .add_systems(Update, (spawn_clouds, drift_clouds).chain())
+        .add_systems(
+            Update,
+            (
+                // buttons pressed while rebinding only rebind
+                (
+                    choose_mode,
+                    toggle_fatigue,
+                    toggle_controls,
+                    toggle_pull_mode,
+                )
+                    .run_if(not(resource_exists::<Rebinding>)),
+                update_mode_choice,
+                update_fatigue_ui,
+                update_controls_ui,
+                update_pull_mode_ui,
+                capture_binding.run_if(resource_exists::<Rebinding>),
+                start_rebinding,
+                update_rebinding_ui,
+            )
+                .chain()
+                .run_if(in_state(GameState::Menu)),
+        )
+        .add_systems(PreUpdate, read_actions.after(InputSystem))
         .add_systems(
             Update,
             (
-                animate_bow,
                 draw_bow_area,
                 draw_enemy_area,
-                move_arrows,
-                rotate_arrows,
-                move_enemy,
+                draw_string,
+                fit_selected_arrow.run_if(resource_changed::<ArrowCatalog>),
+                select_arrow.run_if(in_state(GameState::Playing)),
+                draw_explosions,
             )
-                .chain(),
+                .chain()
+                .run_if(in_state(GameState::Playing).or_else(in_state(GameState::Paused))),
+        )
+        // the hud shows how the tick ended
+        .add_systems(
+            FixedUpdate,
+            (
+                update_scoreboard,
+                progress_bow,
+                update_arrow_ui,
+                update_quiver_ui,
+                update_wave_ui,
+                update_mode_ui,
+                show_health_bars,
+            )
+                .after(Simulation)
+                .run_if(in_state(GameState::Playing)),
         )
+        .add_systems(FixedUpdate, on_window_change.before(Simulation));
+    add_simulation(&mut app);
+
+    if let Some(replay) = replay {
+        app.insert_resource(replay);
+    }
+    if let Some(path) = cli::arg_value(&args, "--record") {
+        app.insert_resource(Recorder::new(path, seed))
+            .add_systems(Last, save_recording);
+    }
+    app.run();
+}
+
+/// The systems that play out a game tick by tick, the fixed ticks are what gets recorded
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+struct Simulation;
+
+/// Adds the `Simulation` and its events. A replay runs exactly these, whatever shows it
+fn add_simulation(app: &mut App) {
+    app.configure_sets(FixedUpdate, Simulation.run_if(in_state(GameState::Playing)))
         .add_systems(
             FixedUpdate,
             (
-                update_mouse,
+                update_aim.run_if(not(resource_exists::<Replay>)),
+                replay_input.run_if(resource_exists::<Replay>),
+                record_input.run_if(resource_exists::<Recorder>),
+                fit_areas,
                 shoot_bow,
+                split_arrows,
                 shoot_arrow,
-                check_arrow_collision,
+                (check_arrow_collision, score_kills, collect_analytics).chain(),
                 move_bow_cursor,
-                update_scoreboard,
                 clamp_bow,
                 rotate_bow,
                 check_arrow_bounds,
-                progress_bow,
+                run_waves,
                 spawn_enemy,
-                animate_enemy,
+                face_enemies,
+                unstick_arrows,
+                fade_explosions,
             )
-                .chain(),
+                .chain()
+                .in_set(Simulation),
+        )
+        // the simulation only advances in fixed ticks, so a replay plays out the same
+        .add_systems(
+            FixedUpdate,
+            (
+                pull_bow,
+                animate_sprites,
+                animate_bow,
+                tire_bow,
+                move_arrows,
+                rotate_arrows,
+                follow_path,
+                flock,
+                knock_back,
+                apply_patterns,
+            )
+                .chain()
+                .after(spawn_enemy)
+                .in_set(Simulation),
+        )
+        .add_systems(
+            FixedUpdate,
+            (
+                reload_quiver,
+                drop_pickups.run_if(refills_quiver),
+                move_pickups,
+                collect_pickups.run_if(refills_quiver),
+                limit_landed_arrows,
+                escape_enemies,
+                track_run,
+            )
+                .chain()
+                // both draw from the `GameRng`, a fixed order keeps runs reproducible
+                .after(apply_patterns)
+                .in_set(Simulation),
+        )
+        .add_systems(
+            FixedUpdate,
+            (flash_hits, float_popups)
+                .before(despawn_entities)
+                .in_set(Simulation),
+        )
+        // after the escapes, or a lingering enemy would escape twice
+        .add_systems(
+            FixedUpdate,
+            despawn_entities.after(escape_enemies).in_set(Simulation),
         )
-        .add_systems(FixedUpdate, despawn_entities)
-        .add_systems(FixedUpdate, on_window_change)
         .add_event::<ArrowShotEvent>()
         .add_event::<DespawnEvent>()
-        .run();
+        .add_event::<ArrowHitEvent>()
+        .add_event::<EnemyKilledEvent>()
+        .add_event::<AnimationEvent>()
+        .add_event::<SpawnEnemyEvent>()
+        .add_event::<EnemyEscapedEvent>();
 }
 
 #[derive(Resource, Deref, DerefMut, Reflect)]
@@ -94,6 +410,40 @@ struct G(f32);
 #[derive(Resource, Deref, DerefMut)]
 struct Mouse(Vec2);
 
+/// Where keys or a stick push the bow this tick, at most 1 long
+#[derive(Resource, Deref, DerefMut, Default)]
+struct MoveInput(Vec2);
+
+/// The button that draws the bow, sampled once per fixed tick
+/// so shots line up with the ticks they are recorded and replayed in
+#[derive(Resource, Default)]
+struct DrawButton {
+    pressed: bool,
+    was_pressed: bool,
+    /// The draw was cancelled, letting go doesn't shoot
+    cancelled: bool,
+}
+
+impl DrawButton {
+    fn set(&mut self, pressed: bool, cancelled: bool) {
+        self.was_pressed = self.pressed;
+        self.pressed = pressed;
+        self.cancelled = cancelled;
+    }
+
+    fn pressed(&self) -> bool {
+        self.pressed
+    }
+
+    fn cancelled(&self) -> bool {
+        self.cancelled
+    }
+
+    fn just_released(&self) -> bool {
+        self.was_pressed && !self.pressed && !self.cancelled
+    }
+}
+
 #[derive(Resource, Deref, DerefMut)]
 struct Scoreboard(u32);
 
@@ -109,6 +459,36 @@ struct BowArea(Area);
 #[derive(Resource, Deref, DerefMut)]
 struct EnemyArea(Area);
 
+/// The size of the world the game plays out in, centered on the origin.
+/// It follows the window, but a replay plays out in the size it was recorded in
+#[derive(Resource, Deref, Clone, Copy, PartialEq, Debug)]
+struct Playfield(Vec2);
+
+impl Playfield {
+    fn rect(&self) -> Rect {
+        Rect::from_center_size(Vec2::ZERO, self.0)
+    }
+
+    /// The left quarter
+    fn bow_area(&self) -> BowArea {
+        BowArea(Area::new(
+            Vec2::new(self.x / -2., self.y / 2.),
+            Vec2::new(self.x / -4., self.y / -2.),
+        ))
+    }
+
+    /// The right half, away from the edges
+    fn enemy_area(&self) -> EnemyArea {
+        EnemyArea(
+            Area::new(
+                Vec2::new(0., self.y / 2.),
+                Vec2::new(self.x / 2., self.y / -2.),
+            )
+            .shrink(0.1),
+        )
+    }
+}
+
 #[derive(Clone, Copy, PartialEq)]
 enum Side {
     North,
@@ -151,177 +531,250 @@ impl Area {
     }
 }
 
-#[derive(Component)]
+#[derive(Component, Clone, Copy)]
 struct Path {
     start: Vec2,
     end: Vec2,
 }
 
 impl Path {
+    fn length(&self) -> f32 {
+        (self.end - self.start).length()
+    }
+
     fn reverse(mut self) {
         std::mem::swap(&mut self.start, &mut self.end);
     }
 }
 
-trait PathFindingStrategy {
-    fn find(self, line1: &(Side, Vec2, Vec2), line2: &(Side, Vec2, Vec2)) -> Path;
-    fn find_from_start(self, line1: &Vec2, line2: &(Side, Vec2, Vec2)) -> Path;
+fn pick<T: Clone>(rng: &mut impl Rng, amount: usize, slice: &Vec<T>) -> Vec<usize> {
+    rand::seq::index::sample(rng, slice.length(), amount).into_vec()
 }
 
-#[derive(Resource, Copy, Clone)]
-struct MinLengthPathFinder(f32);
+#[derive(Component)]
+struct Enemy;
 
-impl PathFindingStrategy for MinLengthPathFinder {
-    fn find(self, line1: &(Side, Vec2, Vec2), line2: &(Side, Vec2, Vec2)) -> Path {
-        let min = self.0;
-        let mut start: Vec2;
-        let mut end: Vec2;
-        loop {
-            start = random_point_on_line(line1.1, line1.2);
-            end = random_point_on_line(line2.1, line2.2);
-            if (end - start).length() >= min {
-                break;
-            }
-        }
-        return Path { start, end };
-    }
+#[derive(Resource, Deref)]
+struct EnemyArchetypesHandle(Handle<EnemyArchetypes>);
 
-    fn find_from_start(self, line1: &Vec2, line2: &(Side, Vec2, Vec2)) -> Path {
-        let min = self.0;
-        let mut start: Vec2;
-        let mut end: Vec2;
-        loop {
-            start = *line1;
-            end = random_point_on_line(line2.1, line2.2);
-            if (end - start).length() >= min {
-                break;
-            }
-        }
-        return Path { start, end };
-    }
-}
+/// The points an enemy is worth
+#[derive(Component, Deref)]
+struct ScoreValue(u32);
 
-fn random_point_on_line(from: Vec2, to: Vec2) -> Vec2 {
-    let t = rand::random::<f32>();
-    return from.lerp(to, t);
+#[derive(Component)]
+struct Health {
+    current: f32,
+    max: f32,
 }
 
-fn pick<T: Clone>(amount: usize, slice: &Vec<T>) -> Vec<usize> {
-    let mut rng = rand::thread_rng();
+impl Health {
+    fn new(max: f32) -> Self {
+        Health { current: max, max }
+    }
 
-    rand::seq::index::sample(&mut rng, slice.length(), amount).into_vec()
+    fn is_dead(&self) -> bool {
+        self.current <= 0.
+    }
 }
 
-#[derive(Resource, Deref, DerefMut, Reflect)]
-struct SpawnTimer(Timer);
+/// The world space bar showing the health of a damaged enemy
+#[derive(Component, Deref)]
+struct HealthBar(Entity);
 
+/// Tints the sprite of an enemy that just got hit until the timer runs out
 #[derive(Component)]
-struct Enemy;
+struct HitFlash {
+    timer: Timer,
+    /// The color of the sprite before the flash, restored after it
+    color: Color,
+}
+
+/// Starts the hit flash of `enemy`, or starts it over if it is already flashing
+fn flash_hit(commands: &mut Commands, enemy: Entity) {
+    commands.entity(enemy).add(|mut enemy: EntityWorldMut| {
+        if let Some(mut flash) = enemy.get_mut::<HitFlash>() {
+            flash.timer.reset();
+            return;
+        }
+        let color = enemy
+            .get::<Sprite>()
+            .map_or(Color::WHITE, |sprite| sprite.color);
+        enemy.insert(HitFlash {
+            timer: Timer::from_seconds(HIT_FLASH_TIME, TimerMode::Once),
+            color,
+        });
+    });
+}
 
+/// A push an enemy got from a hit. Slows down over time
+#[derive(Component, Deref, DerefMut)]
+struct Knockback(Vec2);
+
+#[allow(clippy::too_many_arguments)]
 fn spawn_enemy(
     mut commands: Commands,
-    time: Res<Time>,
-    path_finder: Res<MinLengthPathFinder>,
-    mut timer: ResMut<SpawnTimer>,
+    mut spawns: EventReader<SpawnEnemyEvent>,
+    mut waves: ResMut<WaveState>,
+    path_finder: Res<PathFinder>,
+    mut rng: ResMut<GameRng>,
     mut texture_atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
     asset_server: Res<AssetServer>,
     enemy_area: Res<EnemyArea>,
+    bow: Query<&Transform, With<Bow>>,
 ) {
-    timer.tick(time.delta());
-    if timer.just_finished() {
-        let texture = asset_server.load("enemy/enemy.png");
-        let layout = TextureAtlasLayout::from_grid(Vec2::new(500. / 8., 50.), 8, 1, None, None);
+    let rng = &mut **rng;
+    for SpawnEnemyEvent { archetype, speed } in spawns.read() {
+        let texture = asset_server.load(&archetype.sprite_sheet);
+        let frames = &archetype.frames;
+        let layout = TextureAtlasLayout::from_grid(
+            Vec2::new(frames.width, frames.height),
+            frames.columns,
+            frames.rows,
+            None,
+            None,
+        );
         let texture_atlas_layout = texture_atlas_layouts.add(layout);
-
-        let animation_indices = AnimationIndices { first: 0, last: 7 };
+        let clips = asset_server.load(&archetype.clips);
 
         let walls = enemy_area.walls.to_vec();
-        let is = &pick(2, &walls)[..];
+        let is = &pick(rng, 2, &walls)[..];
         let start_wall = &walls[is[0]];
         let end_wall = &walls[is[1]];
 
-        let path = path_finder.find(start_wall, end_wall);
-        let size = thread_rng().gen_range((500. / 8.)..100.);
+        let context = PathContext::new(None, bow.get_single().ok());
+        let Some(path) = path_finder.find(rng, start_wall, end_wall, &context) else {
+            continue;
+        };
+        waves.spawned += 1;
+        let group = match archetype.movement {
+            Movement::Flock { size } => size.max(1),
+            _ => 1,
+        };
 
-        commands.spawn((
-            SpriteSheetBundle {
-                texture,
-                atlas: TextureAtlas {
-                    layout: texture_atlas_layout,
-                    index: animation_indices.first,
-                },
-                transform: Transform {
-                    translation: path.start.extend(0.),
-                    ..default()
-                },
-                sprite: Sprite {
-                    custom_size: Some(Vec2::new(size, size)),
+        let mut flock_id = None;
+        for _ in 0..group {
+            let size = random_in(&archetype.size, rng);
+            let speed = random_in(&archetype.speed, rng) * speed;
+            // members of a flock start close to each other instead of on top of each other
+            let start = if group > 1 {
+                path.start + Vec2::new(rng.gen_range(-40.0..40.), rng.gen_range(-40.0..40.))
+            } else {
+                path.start
+            };
+
+            let mut enemy = commands.spawn((
+                SpriteSheetBundle {
+                    texture: texture.clone(),
+                    atlas: TextureAtlas {
+                        layout: texture_atlas_layout.clone(),
+                        index: 0,
+                    },
+                    transform: Transform {
+                        translation: start.extend(0.),
+                        ..default()
+                    },
+                    sprite: Sprite {
+                        custom_size: Some(Vec2::new(size, size)),
+                        ..default()
+                    },
                     ..default()
                 },
-                ..default()
-            },
-            Speed(200.),
-            AnimationTimer(Timer::from_seconds(0.2, TimerMode::Repeating)),
-            animation_indices,
-            path,
-            EndsAt(end_wall.0.clone()),
-            Enemy,
-        ));
+                Speed(speed),
+                Health::new(archetype.health),
+                ScoreValue(archetype.score),
+                archetype.movement,
+                Name::new(archetype.name.clone()),
+                Animator::new(clips.clone(), &archetype.animation)
+                    .with_speed(archetype.animation_speed),
+                path,
+                EndsAt(end_wall.0),
+                Anchor(start),
+                FlightTime::default(),
+                Enemy,
+                InGame,
+            ));
+            if group > 1 {
+                let id = *flock_id.get_or_insert(FlockId(enemy.id()));
+                enemy.insert((id, Vel((path.end - path.start).normalize_or_zero() * speed)));
+            }
+        }
+    }
+}
+
+fn knock_back(time: Res<Time>, mut query: Query<(&mut Anchor, &mut Knockback)>) {
+    for (mut anchor, mut knockback) in &mut query {
+        **anchor += **knockback * time.delta_seconds();
+        **knockback *= (-KNOCKBACK_DAMPING * time.delta_seconds()).exp();
     }
 }
 
-fn animate_enemy(
+fn flash_hits(
     time: Res<Time>,
-    mut query: Query<
-        (
-            &AnimationIndices,
-            &mut Sprite,
-            &mut AnimationTimer,
-            &mut TextureAtlas,
-            &Path,
-        ),
-        With<Enemy>,
-    >,
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut Sprite, &mut HitFlash)>,
 ) {
-    for (indices, mut sprite, mut timer, mut atlas, path) in &mut query {
-        // sprite looks to the left by default
-        sprite.flip_x = (path.end - path.start).x > 0.;
-
-        timer.tick(time.delta());
-        if timer.just_finished() {
-            if atlas.index < indices.last {
-                atlas.index += 1;
-            } else {
-                atlas.index = indices.first;
-            }
+    for (entity, mut sprite, mut flash) in &mut query {
+        flash.timer.tick(time.delta());
+        if flash.timer.finished() {
+            sprite.color = flash.color;
+            commands.entity(entity).remove::<HitFlash>();
+        } else {
+            sprite.color = HIT_FLASH_COLOR;
         }
     }
 }
 
-fn move_enemy(
-    time: Res<Time>,
-    path_finder: Res<MinLengthPathFinder>,
-    enemy_area: Res<EnemyArea>,
-    mut query: Query<(&Speed, &mut Transform, &mut Path, &EndsAt), With<Enemy>>,
+fn show_health_bars(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<ProgressBarMaterial>>,
+    enemies: Query<(Entity, &Health, &Sprite, Option<&HealthBar>), Changed<Health>>,
+    mut bars: Query<&mut ProgressBar>,
 ) {
-    for (speed, mut transform, mut path, EndsAt(side)) in &mut query {
-        let vel = (path.end - path.start).normalize() * **speed * time.delta_seconds();
-        transform.translation += vel.extend(0.);
-        let px_threshold = 5.;
-        if (transform.translation - path.end.extend(0.)).length() < px_threshold {
-            let start = path.end;
-            let walls: Vec<&(Side, Vec2, Vec2)> = enemy_area
-                .walls
-                .iter()
-                .filter(|wall| wall.0 != *side)
-                .collect();
-            let index = pick(1, &walls)[..][0];
-            let end_wall = walls[..][index];
-            *path = path_finder.find_from_start(&start, end_wall);
+    for (enemy, health, sprite, bar) in &enemies {
+        if health.current >= health.max {
+            continue;
+        }
+
+        let progress = health.current / health.max;
+        match bar.and_then(|bar| bars.get_mut(**bar).ok()) {
+            Some(mut bar) => {
+                bar.set_progress(progress);
+            }
+            None => {
+                let width = sprite.custom_size.map_or(50., |size| size.x);
+                let mut bar = ProgressBar::new(vec![(1, Color::GREEN)]);
+                bar.empty_color = Color::RED;
+                bar.set_progress(progress);
+                let style = Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Px(width),
+                    height: Val::Px(6.),
+                    ..default()
+                };
+                let bar = commands
+                    .spawn((
+                        ProgressBarBundle::new(style, bar, &mut materials),
+                        ProgressBarAnchor {
+                            target: enemy,
+                            offset: Vec2::new(0., width / 2.),
+                        },
+                        InGame,
+                    ))
+                    .id();
+                commands.entity(enemy).insert(HealthBar(bar));
+            }
         }
     }
 }
 
+/// Turns enemies the way they fly
+fn face_enemies(mut query: Query<(&mut Sprite, &Path), With<Enemy>>) {
+    for (mut sprite, path) in &mut query {
+        // sprite looks to the left by default
+        sprite.flip_x = (path.end - path.start).x > 0.;
+    }
+}
+
 #[derive(Component)]
 struct PullProgressBar;
 
@@ -334,16 +787,269 @@ struct Fixed(bool);
 #[derive(Component)]
 struct Arrow;
 
-#[derive(Event, Default)]
+/// What an arrow does when it hits an enemy or a wall
+#[derive(Component, Clone, Copy, PartialEq, Debug, Default, Reflect)]
+enum ArrowKind {
+    /// Hits a single enemy and vanishes
+    #[default]
+    Normal,
+    /// Passes through this many enemies before it vanishes
+    Piercing(u32),
+    /// Reflects off the edges of the playfield.
+    /// Keeps `restitution` of its speed into the wall on every bounce
+    Bouncing { restitution: f32, bounces: u32 },
+    /// Embeds into whatever it hits and stays there for `linger` seconds
+    Sticking { linger: f32 },
+    /// Takes every enemy within `radius` of the hit down with it
+    Explosive { radius: f32 },
+}
+
+/// The index into the `ArrowCatalog` of the arrow the next shot uses
+#[derive(Resource, Deref, DerefMut)]
+struct SelectedArrow(usize);
+
+#[derive(Component)]
+struct ArrowUi;
+
+/// The arrows the player has left
+#[derive(Resource, Reflect)]
+struct Quiver {
+    capacity: u32,
+    arrows: u32,
+    /// Adds an arrow every time it finishes while the quiver is not full
+    reload: Timer,
+    /// Seconds the quiver display keeps flashing after a dry fire
+    dry_fire: f32,
+}
+
+impl Quiver {
+    fn new(capacity: u32, reload: f32) -> Self {
+        Quiver {
+            capacity,
+            arrows: capacity,
+            reload: Timer::from_seconds(reload, TimerMode::Repeating),
+            dry_fire: 0.,
+        }
+    }
+
+    /// Takes an arrow out of the quiver, returns false if it is empty
+    fn take(&mut self) -> bool {
+        if self.arrows == 0 {
+            self.dry_fire = DRY_FIRE_FLASH_TIME;
+            return false;
+        }
+        self.arrows -= 1;
+        true
+    }
+
+    fn refill(&mut self, arrows: u32) {
+        self.arrows = (self.arrows + arrows).min(self.capacity);
+    }
+
+    fn is_full(&self) -> bool {
+        self.arrows >= self.capacity
+    }
+}
+
+#[derive(Component)]
+struct QuiverUi;
+
+#[derive(Component)]
+struct WaveUi;
+
+/// Arrows lying around that refill the quiver once the bow reaches them
+#[derive(Component, Deref, DerefMut)]
+struct AmmoPickup(u32);
+
+/// A pickup that flies to the bow on its own once the timer runs out
+#[derive(Component, Deref, DerefMut)]
+struct Homing(Timer);
+
+/// An arrow that fell to the ground, with the time it landed at
+#[derive(Component, Deref, DerefMut)]
+struct Landed(f32);
+
+/// How much damage an arrow deals on hit at `FULL_DAMAGE_SPEED`
+#[derive(Component, Deref, DerefMut)]
+struct Damage(f32);
+
+/// The enemies an arrow already went through, so it does not hit them again
+#[derive(Component, Deref, DerefMut, Default)]
+struct HitEnemies(Vec<Entity>);
+
+/// An arrow that forks into `arrow`s once the timer runs out
+#[derive(Component)]
+struct Splitting {
+    timer: Timer,
+    split: Split,
+    arrow: ArrowType,
+}
+
+/// The blast of an explosive arrow, drawn until the timer runs out
+#[derive(Component)]
+struct Explosion {
+    radius: f32,
+    timer: Timer,
+}
+
+/// Forks an arrow into `count` arrows `after` seconds of flight.
+/// The new arrows fan out over `spread` radians around the old direction
+#[derive(Clone, Copy, PartialEq, Debug, Reflect)]
+struct Split {
+    after: f32,
+    count: u32,
+    spread: f32,
+}
+
+/// Everything that makes one type of arrow different from another
+#[derive(Clone, PartialEq, Debug, Reflect)]
+struct ArrowType {
+    name: String,
+    sprite: String,
+    /// Tint applied to the sprite
+    color: Color,
+    /// Multiplies the gravity `G`
+    gravity: f32,
+    /// Multiplies the speed a pull gives the arrow
+    speed: f32,
+    damage: f32,
+    kind: ArrowKind,
+    split: Option<Split>,
+}
+
+/// All arrows the player can choose from
+#[derive(Resource, Deref, DerefMut, Reflect)]
+struct ArrowCatalog(Vec<ArrowType>);
+
+impl Default for ArrowCatalog {
+    fn default() -> Self {
+        let standard = ArrowType {
+            name: "Standard".into(),
+            sprite: "bow/arrow.png".into(),
+            color: Color::WHITE,
+            gravity: 1.,
+            speed: 1.,
+            damage: 1.,
+            kind: ArrowKind::Normal,
+            split: None,
+        };
+
+        ArrowCatalog(vec![
+            standard.clone(),
+            ArrowType {
+                name: "Heavy".into(),
+                color: Color::GRAY,
+                gravity: 1.8,
+                speed: 0.8,
+                damage: 2.,
+                ..standard.clone()
+            },
+            ArrowType {
+                name: "Light".into(),
+                color: Color::rgb(0.8, 1.0, 0.8),
+                gravity: 0.6,
+                speed: 1.3,
+                damage: 0.5,
+                ..standard.clone()
+            },
+            ArrowType {
+                name: "Explosive".into(),
+                color: Color::ORANGE_RED,
+                gravity: 1.2,
+                speed: 0.9,
+                kind: ArrowKind::Explosive { radius: 120. },
+                ..standard.clone()
+            },
+            ArrowType {
+                name: "Split".into(),
+                color: Color::CYAN,
+                split: Some(Split {
+                    after: 0.4,
+                    count: 3,
+                    spread: 0.5,
+                }),
+                ..standard.clone()
+            },
+            ArrowType {
+                name: "Piercing".into(),
+                color: Color::YELLOW,
+                kind: ArrowKind::Piercing(2),
+                ..standard.clone()
+            },
+            ArrowType {
+                name: "Bouncing".into(),
+                color: Color::GREEN,
+                kind: ArrowKind::Bouncing {
+                    restitution: 0.7,
+                    bounces: 3,
+                },
+                ..standard.clone()
+            },
+            ArrowType {
+                name: "Sticking".into(),
+                color: Color::PINK,
+                kind: ArrowKind::Sticking { linger: 5. },
+                ..standard
+            },
+        ])
+    }
+}
+
+/// Where an arrow left the bow, split arrows keep the one of the arrow they split from
+#[derive(Component, Deref, DerefMut)]
+struct ShotFrom(Vec2);
+
+/// The shot an arrow belongs to, named after the arrow that left the bow.
+/// Split arrows keep the shot of the arrow they split from
+#[derive(Component, Deref, Clone, Copy)]
+struct Shot(Entity);
+
+/// Where an arrow was the last time its bounds were checked
+#[derive(Component, Deref, DerefMut)]
+struct PrevPos(Vec2);
+
+/// An arrow that got stuck and is only decoration until the timer runs out
+#[derive(Component, Deref, DerefMut)]
+struct Stuck(Timer);
+
+#[derive(Event)]
 struct ArrowShotEvent {
     pos: Vec2,
+    /// Where the shot started, `pos` unless the arrow split off another one
+    origin: Vec2,
+    /// How long the bow was drawn, `None` for arrows split off another one
+    pull_time: Option<f32>,
+    /// The shot of the arrow this one split off, `None` for arrows that left the bow
+    shot: Option<Entity>,
     angle: Quat,
     velocity: Vec2,
+    arrow: ArrowType,
 }
 
 #[derive(Event, Deref, DerefMut)]
 struct DespawnEvent(Entity);
 
+/// An arrow hitting an enemy, whether or not the hit kills it
+#[derive(Event)]
+struct ArrowHitEvent {
+    /// Whether this is the first enemy the shot hit, with any of the arrows it split into
+    first: bool,
+    /// How far the arrow flew from where it was shot
+    shot_distance: f32,
+}
+
+#[derive(Event)]
+struct EnemyKilledEvent {
+    pos: Vec2,
+    /// The arrow that dealt the final blow, directly or by its blast
+    arrow: Entity,
+    value: u32,
+    size: f32,
+    /// How far the enemy was from where the arrow was shot
+    shot_distance: f32,
+    headshot: bool,
+}
+
 #[derive(Component, Deref, DerefMut)]
 struct Vel(Vec2);
 
@@ -353,86 +1059,115 @@ struct Speed(f32);
 #[derive(Component, Deref, DerefMut)]
 struct Acc(Vec2);
 
-// Animation
-#[derive(Component)]
-struct AnimationIndices {
-    first: usize,
-    last: usize,
-}
-
-#[derive(Component, Deref, DerefMut)]
-struct AnimationTimer(Timer);
-
 #[derive(Component)]
 struct MainCamera;
 
 fn setup(
     window: Query<&Window>,
+    replay: Option<Res<Replay>>,
     mut commands: Commands,
-    mut texture_atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
-    mut progress_bar_materials: ResMut<Assets<ProgressBarMaterial>>,
     asset_server: Res<AssetServer>,
+    images: Res<Assets<Image>>,
+    layouts: Res<Assets<TextureAtlasLayout>>,
 ) {
     commands.spawn((Camera2dBundle::default(), MainCamera));
+    commands.insert_resource(BowAtlas::load(&asset_server, &images, &layouts));
 
     let win = window.single();
 
-    // Bow
-    let texture = asset_server.load("bow/bow-atlas.png");
-    let layout = TextureAtlasLayout::from_grid(Vec2::new(BOW_SIZE, BOW_SIZE), 3, 3, None, None);
-    let texture_atlas_layout = texture_atlas_layouts.add(layout);
-    // Use only the subset of sprites in the sheet that make up the run animation
-    let animation_indices = AnimationIndices { first: 0, last: 7 };
-    commands.spawn((
-        SpriteSheetBundle {
-            texture,
-            atlas: TextureAtlas {
-                layout: texture_atlas_layout,
-                index: animation_indices.first,
-            },
-            ..default()
-        },
-        animation_indices,
-        Bow,
-        BowPullTime::default(),
-        AnimationTimer(Timer::from_seconds(
-            BOW_FULL_PULL_TIME / 8.,
-            TimerMode::Once,
-        )),
-        Fixed(false),
+    commands.insert_resource(EnemyArchetypesHandle(
+        asset_server.load("enemies.archetypes.ron"),
+    ));
+    commands.insert_resource(WavesHandle(asset_server.load("default.waves.ron")));
+    commands.insert_resource(BackgroundHandle(
+        asset_server.load("default.background.ron"),
     ));
+    let size = Vec2::new(win.width(), win.height());
+    commands.insert_resource(Sky::new(size));
 
-    commands.insert_resource(BowArea(Area::new(
-        Vec2::new(0., win.height() / 2.),
-        Vec2::new(win.width() / -4., win.height() / -2.),
-    )));
-
-    let enemy_area = EnemyArea(
-        Area::new(
-            Vec2::new(0., win.height() / 2.),
-            Vec2::new(win.width() / 2., win.height() / -2.),
-        )
-        .shrink(0.1),
-    );
-    let path_finder = MinLengthPathFinder(enemy_area.rect.width() / 2.);
+    let playfield = Playfield(replay.and_then(|replay| replay.playfield()).unwrap_or(size));
+    commands.insert_resource(playfield.bow_area());
+    let enemy_area = playfield.enemy_area();
+    let path_finder = PathFinder::MinLength(MinLength(enemy_area.rect.width() / 2.));
     commands.insert_resource(enemy_area);
     commands.insert_resource(path_finder);
+    commands.insert_resource(playfield);
+}
+
+/// Spawns the bow and the hud and resets everything a previous game left behind
+#[allow(clippy::too_many_arguments)]
+fn start_game(
+    mut commands: Commands,
+    seed: Res<Seed>,
+    mode: Res<GameMode>,
+    fatigue: Res<Fatigue>,
+    controls: Res<ControlScheme>,
+    pull_mode: Res<PullMode>,
+    playfield: Res<Playfield>,
+    mut rng: ResMut<GameRng>,
+    recorder: Option<ResMut<Recorder>>,
+    bow_atlas: Res<BowAtlas>,
+    mut progress_bar_materials: ResMut<Assets<ProgressBarMaterial>>,
+    asset_server: Res<AssetServer>,
+) {
+    // every game with the same seed plays out the same
+    *rng = GameRng::new(*seed);
+    if let Some(mut recorder) = recorder {
+        recorder.recording.frames.clear();
+        recorder.recording.mode = *mode;
+        recorder.recording.fatigue = *fatigue;
+        recorder.recording.controls = *controls;
+        recorder.recording.pull_mode = *pull_mode;
+        recorder.recording.playfield = Some((playfield.x, playfield.y));
+    }
+    commands.insert_resource(Scoreboard(0));
+    commands.insert_resource(Combo::default());
+    commands.insert_resource(Run::new(*mode));
+    commands.insert_resource(WaveState::default());
+    commands.insert_resource(SelectedArrow(0));
+    let capacity = match *mode {
+        GameMode::LimitedArrows(arrows) => arrows,
+        _ => QUIVER_CAPACITY,
+    };
+    commands.insert_resource(Quiver::new(capacity, QUIVER_RELOAD_TIME));
+    commands.insert_resource(DrawButton::default());
+
+    // Bow
+    commands.spawn((
+        SpriteSheetBundle {
+            texture: bow_atlas.texture.clone(),
+            atlas: TextureAtlas {
+                layout: bow_atlas.layout.clone(),
+                index: 0,
+            },
+            ..default()
+        },
+        Animator::new(asset_server.load("bow/bow.clips.ron"), "idle"),
+        Bow,
+        BowState::default(),
+        BowPullTime::default(),
+        DragAnchor::default(),
+        Tired::default(),
+        Fixed(false),
+        InGame,
+    ));
 
     let bar = ProgressBar::new(vec![(200, Color::BLUE)]);
     let style = Style {
         position_type: PositionType::Absolute,
-        width: Val::Px(BOW_SIZE),
         height: Val::Px(20.),
         ..default()
     };
     commands.spawn((
         PullProgressBar,
         ProgressBarBundle::new(style, bar, &mut progress_bar_materials),
+        InGame,
     ));
 
     // Scoreboard
     commands.spawn((
         ScoreboardUi,
+        InGame,
         TextBundle::from_sections([
             TextSection::new(
                 "Score: ",
@@ -447,6 +1182,19 @@ fn setup(
                 color: SCORE_COLOR,
                 ..default()
             }),
+            TextSection::new(
+                "  Combo: ",
+                TextStyle {
+                    font_size: SCOREBOARD_FONT_SIZE,
+                    color: TEXT_COLOR,
+                    ..default()
+                },
+            ),
+            TextSection::from_style(TextStyle {
+                font_size: SCOREBOARD_FONT_SIZE,
+                color: SCORE_COLOR,
+                ..default()
+            }),
         ])
         .with_style(Style {
             position_type: PositionType::Absolute,
@@ -455,79 +1203,90 @@ fn setup(
             ..default()
         }),
     ));
+
+    commands.spawn((ArrowUi, InGame, hud_line("Arrow: ", 1)));
+    commands.spawn((QuiverUi, InGame, hud_line("Quiver: ", 2)));
+    commands.spawn((WaveUi, InGame, hud_line("Wave: ", 3)));
+    commands.spawn((ModeUi, InGame, hud_line("", 4)));
+}
+
+/// A label and a value below the scoreboard, `row` lines down from the top
+fn hud_line(label: &str, row: u32) -> TextBundle {
+    TextBundle::from_sections([
+        TextSection::new(
+            label,
+            TextStyle {
+                font_size: SCOREBOARD_FONT_SIZE,
+                color: TEXT_COLOR,
+                ..default()
+            },
+        ),
+        TextSection::from_style(TextStyle {
+            font_size: SCOREBOARD_FONT_SIZE,
+            color: SCORE_COLOR,
+            ..default()
+        }),
+    ])
+    .with_style(Style {
+        position_type: PositionType::Absolute,
+        top: Val::Px(5. + row as f32 * (SCOREBOARD_FONT_SIZE + 5.)),
+        left: SCOREBOARD_TEXT_PADDING,
+        ..default()
+    })
 }
 
 fn on_window_change(
     window: Query<&Window, Changed<Window>>,
-    mut bow_area: ResMut<BowArea>,
-    mut enemy_area: ResMut<EnemyArea>,
+    replay: Option<Res<Replay>>,
+    mut playfield: ResMut<Playfield>,
+    mut sky: ResMut<Sky>,
+    mut clouds: Query<&mut Transform, With<Cloud>>,
 ) {
     for win in &window {
-        *bow_area = BowArea(Area::new(
-            Vec2::new(win.width() / -2., win.height() / 2.),
-            Vec2::new(win.width() / -4., win.height() / -2.),
-        ));
-        *enemy_area = EnemyArea(
-            Area::new(
-                Vec2::new(0., win.height() / 2.),
-                Vec2::new(win.width() / 2., win.height() / -2.),
-            )
-            .shrink(0.1),
-        )
+        let size = Vec2::new(win.width(), win.height());
+        sky.resize(size, &mut clouds);
+        // a replay resizes the playfield when the recording did
+        if replay.is_none() {
+            playfield.set_if_neq(Playfield(size));
+        }
     }
 }
 
-fn animate_bow(
-    time: Res<Time>,
-    mut query: Query<
-        (
-            &AnimationIndices,
-            &mut BowPullTime,
-            &mut AnimationTimer,
-            &mut TextureAtlas,
-            &Fixed,
-        ),
-        With<Bow>,
-    >,
+/// Fits the bow and enemy areas to a resized playfield
+fn fit_areas(
+    playfield: Res<Playfield>,
+    mut bow_area: ResMut<BowArea>,
+    mut enemy_area: ResMut<EnemyArea>,
+    mut path_finder: ResMut<PathFinder>,
 ) {
-    // I could probably also do something like With<Fixed> and then insert the BowPullTime
-    // Component later and remove it after the shot
-    for (indices, mut pull_time, mut timer, mut atlas, fixed) in &mut query {
-        if **fixed {
-            timer.tick(time.delta());
-            **pull_time += time.delta().as_secs_f32();
-            **pull_time = pull_time.clamp(0.0, BOW_FULL_PULL_TIME);
-            if timer.just_finished() {
-                if atlas.index < indices.last {
-                    atlas.index += 1;
-                    timer.reset();
-                };
-            }
-        } else {
-            atlas.index = indices.first;
-            **pull_time = 0.;
-            timer.reset();
-        }
+    // not a run condition, those would also see and forget a resize in the menu
+    if !playfield.is_changed() {
+        return;
     }
+    *bow_area = playfield.bow_area();
+    *enemy_area = playfield.enemy_area();
+    path_finder.set_min(enemy_area.rect.width() / 2.);
 }
 
 fn progress_bow(
-    time: Res<Time>,
     window: Query<&Window>,
-    bow_query: Query<(&Fixed, &Transform), With<Bow>>,
+    layouts: Res<Assets<TextureAtlasLayout>>,
+    bow_query: Query<(&Fixed, &Transform, &BowPullTime, &TextureAtlas), With<Bow>>,
     mut progress_query: Query<(&mut ProgressBar, &mut Style), With<PullProgressBar>>,
 ) {
     let win = window.single();
-    let (fixed, bow_transform) = bow_query.single();
+    let (fixed, bow_transform, pull_time, atlas) = bow_query.single();
+    let bow_size = atlas::frame_size(atlas, &layouts);
     let (mut progress, mut style) = progress_query.single_mut();
 
     if **fixed {
         // I couldn't get the parent child relationship to work properly for transforms, so
         // I map the "normal" carthesian system into the ui one
         style.top =
-            Val::Px((bow_transform.translation.y - win.height() / 2.).abs() + BOW_SIZE / 2.);
-        style.left = Val::Px(bow_transform.translation.x + win.width() / 2. - BOW_SIZE / 2.);
-        progress.increase_progress(time.delta_seconds() / BOW_FULL_PULL_TIME);
+            Val::Px((bow_transform.translation.y - win.height() / 2.).abs() + bow_size.y / 2.);
+        style.left = Val::Px(bow_transform.translation.x + win.width() / 2. - bow_size.x / 2.);
+        style.width = Val::Px(bow_size.x);
+        progress.set_progress(**pull_time / BOW_FULL_PULL_TIME);
     } else {
         progress.reset();
     }
@@ -545,64 +1304,107 @@ fn draw_enemy_area(enemy_area: Res<EnemyArea>, mut gizmos: Gizmos) {
     }
 }
 
-fn update_mouse(
+/// Moves the aim with the cursor, keys or sticks and draws the bow, whatever is bound to it
+#[allow(clippy::too_many_arguments)]
+fn update_aim(
+    time: Res<Time>,
+    actions: Res<ActionState>,
+    bindings: Res<Bindings>,
+    mut cursor_moves: EventReader<CursorMoved>,
     mut mouse: ResMut<Mouse>,
+    mut movement: ResMut<MoveInput>,
+    mut button: ResMut<DrawButton>,
     window: Query<&Window, With<PrimaryWindow>>,
     camera_q: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
 ) {
     let win = window.single();
     let (camera, camera_transform) = camera_q.single();
 
-    win.cursor_position()
-        .and_then(|cursor| camera.viewport_to_world_2d(camera_transform, cursor))
-        .and_then(|cursor_position| {
-            mouse.x = cursor_position.x;
-            mouse.y = cursor_position.y;
-            Some(())
-        });
+    button.set(actions.drawing(), actions.cancelled());
+    **movement = actions.movement;
+    // a resting mouse doesn't snap the aim back from where a stick moved it
+    let cursor_moved = cursor_moves.read().count() > 0;
+    if cursor_moved && bindings.aim.contains(&DirectionBinding::Mouse) {
+        if let Some(cursor_position) = win
+            .cursor_position()
+            .and_then(|cursor| camera.viewport_to_world_2d(camera_transform, cursor))
+        {
+            **mouse = cursor_position;
+        }
+    }
+    let half_size = Vec2::new(win.width(), win.height()) / 2.;
+    **mouse = (**mouse + actions.aim * bindings.aim_speed * time.delta_seconds())
+        .clamp(-half_size, half_size);
 }
 
+#[allow(clippy::too_many_arguments)]
 fn shoot_bow(
     mouse: Res<Mouse>,
-    window: Query<&Window>,
-    buttons: Res<ButtonInput<MouseButton>>,
-    bow: Query<(&Transform, &Fixed, &BowPullTime), With<Bow>>,
+    controls: Res<ControlScheme>,
+    catalog: Res<ArrowCatalog>,
+    selected: Res<SelectedArrow>,
+    mut quiver: ResMut<Quiver>,
+    mut run: ResMut<Run>,
+    playfield: Res<Playfield>,
+    button: Res<DrawButton>,
+    fatigue: Res<Fatigue>,
+    mut rng: ResMut<GameRng>,
+    bow: Query<(&Transform, &Fixed, &BowPullTime, &Tired), With<Bow>>,
     mut shot_event_writer: EventWriter<ArrowShotEvent>,
 ) {
-    let (tr, fixed, pull_time) = bow.single();
-    let win = window.single();
+    let (tr, fixed, pull_time, tired) = bow.single();
 
-    if **fixed && buttons.just_released(MouseButton::Left) {
-        // 1 second to reach the window from the left to the right
-        let max_vel = win.width();
-        let vel = (max_vel / 4.).lerp(max_vel, **pull_time / BOW_FULL_PULL_TIME);
+    if **fixed && button.just_released() {
+        let Some(arrow) = catalog.get(**selected) else {
+            return;
+        };
+        let aim = controls.aim(tr.translation.xy(), **mouse);
+        // a cursor right on the bow points nowhere, the arrow stays in the quiver
+        if aim == Vec2::ZERO {
+            return;
+        }
+        if !quiver.take() {
+            return;
+        }
+        run.shots += 1;
+
+        // 1 second to cross the playfield from the left to the right
+        let max_vel = playfield.x;
+        let vel = (max_vel / 4.).lerp(max_vel, **pull_time / BOW_FULL_PULL_TIME) * arrow.speed;
 
-        let dir_to_mouse = (tr.translation - mouse.extend(0.)).normalize();
-        let angle = dir_to_mouse.y.atan2(dir_to_mouse.x);
+        let angle = aim.y.atan2(aim.x) + tired.spread(&fatigue, &mut **rng);
 
         let vx = vel * angle.cos();
         let vy = vel * angle.sin();
 
-        print!("vx {:?} vy {:?}\n", vx, vy);
-
         shot_event_writer.send(ArrowShotEvent {
             pos: tr.translation.xy(),
+            origin: tr.translation.xy(),
+            pull_time: Some(**pull_time),
+            shot: None,
             angle: tr.rotation,
             velocity: Vec2::new(vx, vy),
+            arrow: arrow.clone(),
         });
     }
 }
 
 fn move_bow_cursor(
+    time: Res<Time>,
+    controls: Res<ControlScheme>,
     mouse: Res<Mouse>,
-    buttons: Res<ButtonInput<MouseButton>>,
-    mut bow: Query<(&mut Transform, &mut Fixed), With<Bow>>,
+    movement: Res<MoveInput>,
+    button: Res<DrawButton>,
+    mut bow: Query<(&mut Transform, &mut Fixed, &BowState), With<Bow>>,
 ) {
-    let (mut tr, mut fixed) = bow.single_mut();
+    let (mut tr, mut fixed, state) = bow.single_mut();
 
-    **fixed = buttons.pressed(MouseButton::Left);
+    **fixed = button.pressed() && state.can_draw();
 
-    if !**fixed {
+    if let Some(speed) = controls.bow_speed(**fixed) {
+        // `clamp_bow` keeps it inside the bow area
+        tr.translation += (**movement * speed * time.delta_seconds()).extend(0.);
+    } else if !**fixed {
         tr.translation.x = mouse.x;
         tr.translation.y = mouse.y;
     }
@@ -616,15 +1418,22 @@ fn clamp_bow(bow_area: Res<BowArea>, mut bowq: Query<&mut Transform, With<Bow>>)
     );
 }
 
-fn rotate_bow(mouse: Res<Mouse>, mut bow: Query<(&mut Transform, &Fixed), With<Bow>>) {
-    let ms = **mouse;
-    let (mut tr, fixed) = bow.single_mut();
-
-    if **fixed {
-        let pos = tr.translation;
+fn rotate_bow(
+    mouse: Res<Mouse>,
+    fatigue: Res<Fatigue>,
+    controls: Res<ControlScheme>,
+    mut bow: Query<(&mut Transform, &Fixed, &Tired), With<Bow>>,
+) {
+    let (mut tr, fixed, tired) = bow.single_mut();
 
-        let dir_to_mouse = (ms - pos.xy()).normalize();
-        let angle = dir_to_mouse.y.atan2(dir_to_mouse.x) - std::f32::consts::PI;
+    if **fixed || controls.aims_freely() {
+        let aim = controls.aim(tr.translation.xy(), **mouse);
+        // a cursor right on the bow points nowhere, it keeps aiming where it did
+        if aim == Vec2::ZERO {
+            return;
+        }
+        // the bow sprite points the way it shoots
+        let angle = aim.y.atan2(aim.x) + tired.shake(&fatigue);
         let rot = Quat::from_rotation_z(angle);
 
         tr.rotation = rot;
@@ -638,19 +1447,253 @@ fn shoot_arrow(
     asset_server: Res<AssetServer>,
 ) {
     for ev in ev_shoot.read() {
-        commands.spawn((
+        let arrow = &ev.arrow;
+        let mut entity = commands.spawn((
             Arrow,
             SpriteBundle {
-                texture: asset_server.load("bow/arrow.png"),
+                texture: asset_server.load(&arrow.sprite),
                 transform: Transform::from_translation(ev.pos.extend(0.0)).with_rotation(ev.angle),
+                sprite: Sprite {
+                    color: arrow.color,
+                    ..default()
+                },
                 ..default()
             },
             Vel(ev.velocity),
-            Acc(Vec2::new(0., -**g)),
+            Acc(Vec2::new(0., -**g * arrow.gravity)),
+            PrevPos(ev.pos),
+            ShotFrom(ev.origin),
+            Damage(arrow.damage),
+            HitEnemies::default(),
+            arrow.kind,
+            InGame,
+        ));
+        let shot = ev.shot.unwrap_or(entity.id());
+        entity.insert(Shot(shot));
+        if let Some(split) = arrow.split {
+            entity.insert(Splitting {
+                timer: Timer::from_seconds(split.after, TimerMode::Once),
+                split,
+                arrow: ArrowType {
+                    split: None,
+                    ..arrow.clone()
+                },
+            });
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn split_arrows(
+    time: Res<Time>,
+    mut arrows: Query<(Entity, &Transform, &Vel, &ShotFrom, &Shot, &mut Splitting), With<Arrow>>,
+    mut shots: EventWriter<ArrowShotEvent>,
+    mut despawns: EventWriter<DespawnEvent>,
+) {
+    for (entity, tr, vel, origin, shot, mut splitting) in &mut arrows {
+        splitting.timer.tick(time.delta());
+        if !splitting.timer.just_finished() {
+            continue;
+        }
+
+        let Split { count, spread, .. } = splitting.split;
+        for i in 0..count {
+            let t = if count > 1 {
+                i as f32 / (count - 1) as f32
+            } else {
+                0.5
+            };
+            let turn = Vec2::from_angle((t - 0.5) * spread);
+            shots.send(ArrowShotEvent {
+                pos: tr.translation.xy(),
+                origin: **origin,
+                pull_time: None,
+                shot: Some(**shot),
+                angle: tr.rotation,
+                velocity: turn.rotate(**vel),
+                arrow: splitting.arrow.clone(),
+            });
+        }
+        despawns.send(DespawnEvent(entity));
+    }
+}
+
+fn select_arrow(
+    keys: Res<ButtonInput<KeyCode>>,
+    actions: Res<ActionState>,
+    mut wheel: EventReader<MouseWheel>,
+    catalog: Res<ArrowCatalog>,
+    mut selected: ResMut<SelectedArrow>,
+) {
+    const DIGITS: [KeyCode; 9] = [
+        KeyCode::Digit1,
+        KeyCode::Digit2,
+        KeyCode::Digit3,
+        KeyCode::Digit4,
+        KeyCode::Digit5,
+        KeyCode::Digit6,
+        KeyCode::Digit7,
+        KeyCode::Digit8,
+        KeyCode::Digit9,
+    ];
+
+    for (index, key) in DIGITS.iter().enumerate() {
+        if keys.just_pressed(*key) && index < catalog.len() {
+            **selected = index;
+        }
+    }
+
+    let scrolled: f32 = wheel.read().map(|ev| ev.y).sum();
+    if scrolled != 0. {
+        **selected = cycle(**selected, scrolled.signum() as isize, catalog.len());
+    }
+    if actions.just_pressed(Action::CycleArrow) {
+        **selected = cycle(**selected, 1, catalog.len());
+    }
+}
+
+/// Moves `current` by `step` through `len` entries, wrapping around at both ends
+fn cycle(current: usize, step: isize, len: usize) -> usize {
+    if len == 0 {
+        return 0;
+    }
+    (current as isize + step).rem_euclid(len as isize) as usize
+}
+
+fn update_arrow_ui(
+    catalog: Res<ArrowCatalog>,
+    selected: Res<SelectedArrow>,
+    mut query: Query<&mut Text, With<ArrowUi>>,
+) {
+    let mut text = query.single_mut();
+    if let Some(arrow) = catalog.get(**selected) {
+        text.sections[1].value = arrow.name.clone();
+        text.sections[1].style.color = arrow.color;
+    }
+}
+
+/// Keeps the selection inside a catalog that was edited, e.g. in the inspector
+fn fit_selected_arrow(catalog: Res<ArrowCatalog>, mut selected: ResMut<SelectedArrow>) {
+    let last = catalog.len().saturating_sub(1);
+    if **selected > last {
+        **selected = last;
+    }
+}
+
+fn reload_quiver(time: Res<Time>, mode: Res<GameMode>, mut quiver: ResMut<Quiver>) {
+    quiver.dry_fire = (quiver.dry_fire - time.delta_seconds()).max(0.);
+
+    if quiver.is_full() || !mode.refills_quiver() {
+        quiver.reload.reset();
+        return;
+    }
+    quiver.reload.tick(time.delta());
+    if quiver.reload.just_finished() {
+        quiver.refill(1);
+    }
+}
+
+fn drop_pickups(
+    mut commands: Commands,
+    mut kills: EventReader<EnemyKilledEvent>,
+    mut rng: ResMut<GameRng>,
+    asset_server: Res<AssetServer>,
+) {
+    for kill in kills.read() {
+        if rng.gen::<f32>() >= PICKUP_DROP_CHANCE {
+            continue;
+        }
+        commands.spawn((
+            SpriteBundle {
+                texture: asset_server.load("bow/arrow.png"),
+                transform: Transform::from_translation(kill.pos.extend(0.))
+                    .with_rotation(Quat::from_rotation_z(std::f32::consts::FRAC_PI_2)),
+                sprite: Sprite {
+                    color: Color::GOLD,
+                    ..default()
+                },
+                ..default()
+            },
+            AmmoPickup(PICKUP_ARROWS),
+            Homing(Timer::from_seconds(0.5, TimerMode::Once)),
+            InGame,
         ));
     }
 }
 
+fn move_pickups(
+    time: Res<Time>,
+    bow: Query<&Transform, (With<Bow>, Without<AmmoPickup>)>,
+    mut pickups: Query<(&mut Transform, &mut Homing), With<AmmoPickup>>,
+) {
+    let bow = bow.single().translation.xy();
+    for (mut tr, mut homing) in &mut pickups {
+        homing.tick(time.delta());
+        if !homing.finished() {
+            continue;
+        }
+        let to_bow = bow - tr.translation.xy();
+        let step = to_bow.clamp_length_max(PICKUP_SPEED * time.delta_seconds());
+        tr.translation += step.extend(0.);
+    }
+}
+
+fn collect_pickups(
+    mut quiver: ResMut<Quiver>,
+    bow: Query<&Transform, With<Bow>>,
+    pickups: Query<(Entity, &Transform, &AmmoPickup)>,
+    mut despawns: EventWriter<DespawnEvent>,
+) {
+    let bow = bow.single().translation.xy();
+    for (entity, tr, pickup) in &pickups {
+        if (tr.translation.xy() - bow).length() < PICKUP_RADIUS {
+            quiver.refill(**pickup);
+            despawns.send(DespawnEvent(entity));
+        }
+    }
+}
+
+fn update_quiver_ui(quiver: Res<Quiver>, mut query: Query<&mut Text, With<QuiverUi>>) {
+    let mut text = query.single_mut();
+    text.sections[1].value = format!("{}/{}", quiver.arrows, quiver.capacity);
+    text.sections[1].style.color = if quiver.dry_fire > 0. {
+        Color::RED
+    } else {
+        SCORE_COLOR
+    };
+}
+
+fn update_wave_ui(state: Res<WaveState>, mut query: Query<&mut Text, With<WaveUi>>) {
+    let mut text = query.single_mut();
+    let wave = state.wave + 1;
+    text.sections[1].value = match &state.phase {
+        WavePhase::Intermission(timer) => {
+            format!("{wave} in {:.0}s", timer.remaining_secs().ceil())
+        }
+        _ => wave.to_string(),
+    };
+}
+
+fn fade_explosions(
+    time: Res<Time>,
+    mut explosions: Query<(Entity, &mut Explosion)>,
+    mut despawns: EventWriter<DespawnEvent>,
+) {
+    for (entity, mut explosion) in &mut explosions {
+        explosion.timer.tick(time.delta());
+        if explosion.timer.finished() {
+            despawns.send(DespawnEvent(entity));
+        }
+    }
+}
+
+fn draw_explosions(explosions: Query<(&Transform, &Explosion)>, mut gizmos: Gizmos) {
+    for (tr, explosion) in &explosions {
+        let radius = explosion.radius * explosion.timer.fraction();
+        gizmos.circle_2d(tr.translation.xy(), radius, Color::ORANGE_RED);
+    }
+}
+
 fn move_arrows(time: Res<Time>, mut arrows: Query<(&mut Transform, &mut Vel, &Acc), With<Arrow>>) {
     for (mut tr, mut vel, acc) in &mut arrows {
         tr.translation.x += vel.x * time.delta_seconds();
@@ -660,52 +1703,396 @@ fn move_arrows(time: Res<Time>, mut arrows: Query<(&mut Transform, &mut Vel, &Ac
 }
 
 fn check_arrow_bounds(
-    arrows: Query<(Entity, &Transform), With<Arrow>>,
-    window: Query<&Window>,
+    time: Res<Time>,
+    mut commands: Commands,
+    mut arrows: Query<
+        (
+            Entity,
+            &mut Transform,
+            &mut Vel,
+            &mut PrevPos,
+            &mut ArrowKind,
+        ),
+        With<Arrow>,
+    >,
+    playfield: Res<Playfield>,
+    bow_area: Res<BowArea>,
     mut despawns: EventWriter<DespawnEvent>,
 ) {
-    let win = window.single();
-    for (entity, tr) in &arrows {
+    let rect = playfield.rect();
+    for (entity, mut tr, mut vel, mut prev, mut kind) in &mut arrows {
         let pos = tr.translation.xy();
 
-        let width = win.width();
-        let height = win.height();
-        let rect = Rect::from_corners(
-            Vec2::new(width / -2., height / 2.),
-            Vec2::new(width / 2., height / -2.),
-        );
+        match *kind {
+            ArrowKind::Bouncing {
+                restitution,
+                ref mut bounces,
+            } if *bounces > 0 => {
+                // only the playfield edges, the enemy area would trap an arrow that flew into it
+                if let Some((pos, reflected)) = reflect_off(rect, **prev, pos, **vel, restitution) {
+                    *bounces -= 1;
+                    tr.translation = pos.extend(tr.translation.z);
+                    **vel = reflected;
+                }
+            }
+            _ if pos.y < rect.min.y && rect.min.x <= pos.x && pos.x <= rect.max.x => {
+                tr.translation.y = rect.min.y;
+                let reachable = bow_area.rect.min.x <= pos.x && pos.x <= bow_area.rect.max.x;
+                land_arrow(&mut commands, entity, time.elapsed_seconds(), reachable);
+                continue;
+            }
+            ArrowKind::Sticking { linger } if !rect.contains(pos) => {
+                let pos = pos.clamp(rect.min, rect.max);
+                tr.translation = pos.extend(tr.translation.z);
+                stick_arrow(&mut commands, entity, linger);
+                continue;
+            }
+            _ => {}
+        }
+
+        if !rect.contains(tr.translation.xy()) {
+            despawns.send(DespawnEvent(entity));
+        }
+        **prev = tr.translation.xy();
+    }
+}
+
+/// Reflects `vel` off the sides of `rect` that were crossed going from `prev` to `pos`.
+///
+/// Returns the position clamped back onto the crossed side together with the new velocity,
+/// or `None` if the arrow did not leave the rect.
+fn reflect_off(
+    rect: Rect,
+    prev: Vec2,
+    pos: Vec2,
+    vel: Vec2,
+    restitution: f32,
+) -> Option<(Vec2, Vec2)> {
+    if !rect.contains(prev) || rect.contains(pos) {
+        return None;
+    }
+
+    let mut pos = pos;
+    let mut vel = vel;
+    if pos.x < rect.min.x || pos.x > rect.max.x {
+        vel.x = -vel.x * restitution;
+        pos.x = pos.x.clamp(rect.min.x, rect.max.x);
+    }
+    if pos.y < rect.min.y || pos.y > rect.max.y {
+        vel.y = -vel.y * restitution;
+        pos.y = pos.y.clamp(rect.min.y, rect.max.y);
+    }
+    Some((pos, vel))
+}
+
+/// Everything that makes an arrow fly and hit, taken away once it stopped
+type Flight = (
+    Arrow,
+    Vel,
+    Acc,
+    PrevPos,
+    ShotFrom,
+    Shot,
+    Damage,
+    HitEnemies,
+    ArrowKind,
+    Splitting,
+);
+
+/// Turns a flying arrow into decoration that stays in place for `linger` seconds
+fn stick_arrow(commands: &mut Commands, arrow: Entity, linger: f32) {
+    commands
+        .entity(arrow)
+        .remove::<Flight>()
+        .insert(Stuck(Timer::from_seconds(linger, TimerMode::Once)));
+}
+
+/// Leaves an arrow lying on the ground for the bow to pick up.
+/// The bow can't leave its area, so an arrow out of its `reachable` flies back after a while
+fn land_arrow(commands: &mut Commands, arrow: Entity, now: f32, reachable: bool) {
+    let mut arrow = commands.entity(arrow);
+    arrow
+        .remove::<Flight>()
+        .insert((AmmoPickup(1), Landed(now)));
+    if !reachable {
+        arrow.insert(Homing(Timer::from_seconds(
+            LANDED_HOMING_DELAY,
+            TimerMode::Once,
+        )));
+    }
+}
+
+fn limit_landed_arrows(landed: Query<(Entity, &Landed)>, mut despawns: EventWriter<DespawnEvent>) {
+    let mut landed: Vec<(Entity, f32)> = landed.iter().map(|(e, at)| (e, **at)).collect();
+    if landed.len() <= MAX_LANDED_ARROWS {
+        return;
+    }
+
+    landed.sort_by(|a, b| a.1.total_cmp(&b.1));
+    for (entity, _) in &landed[..landed.len() - MAX_LANDED_ARROWS] {
+        despawns.send(DespawnEvent(*entity));
+    }
+}
 
-        if !rect.contains(pos) {
+fn unstick_arrows(
+    time: Res<Time>,
+    mut stuck: Query<(Entity, &mut Stuck)>,
+    mut despawns: EventWriter<DespawnEvent>,
+) {
+    for (entity, mut timer) in &mut stuck {
+        timer.tick(time.delta());
+        if timer.finished() {
             despawns.send(DespawnEvent(entity));
         }
     }
 }
 
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
 fn check_arrow_collision(
-    mut score: ResMut<Scoreboard>,
-    arrows: Query<(Entity, &Transform), With<Arrow>>,
-    enemies: Query<(Entity, &Transform, &Sprite), With<Enemy>>,
+    mut commands: Commands,
+    mut arrows: Query<
+        (
+            Entity,
+            &Transform,
+            &Vel,
+            &Damage,
+            &ArrowKind,
+            Option<&mut HitEnemies>,
+        ),
+        With<Arrow>,
+    >,
+    mut enemies: Query<(Entity, &Transform, &Sprite, &mut Health, &ScoreValue), With<Enemy>>,
+    origins: Query<&ShotFrom>,
+    shots: Query<&Shot>,
+    mut hit_shots: Local<HashSet<Entity>>,
     mut despawns: EventWriter<DespawnEvent>,
+    mut hit_events: EventWriter<ArrowHitEvent>,
+    mut kills: EventWriter<EnemyKilledEvent>,
 ) {
-    for (arrow_entity, arrow_tr) in &arrows {
-        for (enemy_entity, enemy_tr, enemy_size) in &enemies {
-            if (arrow_tr.translation.xy() - enemy_tr.translation.xy()).length()
-                < enemy_size.custom_size.unwrap().x
+    // a shot none of whose arrows still fly can't hit again
+    hit_shots.retain(|hit_shot| shots.iter().any(|shot| **shot == *hit_shot));
+    // enemies killed in an earlier tick linger until their despawn went through
+    let already_dead: HashSet<Entity> = enemies
+        .iter()
+        .filter(|(.., health, _)| health.is_dead())
+        .map(|(entity, ..)| entity)
+        .collect();
+
+    let mut candidates = vec![];
+    for (arrow_entity, arrow_tr, vel, damage, _, hit_before) in &arrows {
+        for (enemy_entity, enemy_tr, enemy_size, ..) in &enemies {
+            if already_dead.contains(&enemy_entity)
+                || hit_before.is_some_and(|hit| hit.contains(&enemy_entity))
             {
-                despawns.send(DespawnEvent(arrow_entity));
-                despawns.send(DespawnEvent(enemy_entity));
-                **score += 1
+                continue;
+            }
+            let distance = (arrow_tr.translation.xy() - enemy_tr.translation.xy()).length();
+            if distance < enemy_size.custom_size.unwrap().x {
+                candidates.push(Hit {
+                    arrow: arrow_entity,
+                    enemy: enemy_entity,
+                    distance,
+                    damage: impact_damage(**damage, vel.length()),
+                });
             }
         }
     }
+
+    let resolution = resolve_hits(
+        candidates,
+        |arrow| match arrows.get(arrow) {
+            Ok((_, _, _, _, ArrowKind::Piercing(pierce), hit_before)) => {
+                let hit_before = hit_before.map_or(0, |hit| hit.len() as u32);
+                (1 + pierce).saturating_sub(hit_before)
+            }
+            _ => 1,
+        },
+        |enemy| {
+            enemies
+                .get(enemy)
+                .map_or(0., |(.., health, _)| health.current)
+        },
+    );
+
+    // the arrow that took each enemy down and whether it was a headshot
+    let mut killers: HashMap<Entity, (Entity, bool)> = HashMap::new();
+    let mut blasts = vec![];
+    for hit in &resolution.hits {
+        let Ok((_, arrow_tr, vel, _, kind, hit_before)) = arrows.get_mut(hit.arrow) else {
+            continue;
+        };
+        let pos = arrow_tr.translation.xy();
+        // arrows spawned without a shot are one of their own
+        let shot = shots.get(hit.arrow).map_or(hit.arrow, |shot| **shot);
+        hit_events.send(ArrowHitEvent {
+            first: hit_shots.insert(shot),
+            shot_distance: origins
+                .get(hit.arrow)
+                .map_or(0., |origin| pos.distance(**origin)),
+        });
+        if let Some(mut hit_before) = hit_before {
+            hit_before.push(hit.enemy);
+        }
+        if let ArrowKind::Explosive { radius } = kind {
+            blasts.push((
+                arrow_tr.translation.xy(),
+                *radius,
+                hit.damage,
+                hit.arrow,
+                hit.enemy,
+            ));
+        }
+
+        if let Ok((_, enemy_tr, sprite, mut health, _)) = enemies.get_mut(hit.enemy) {
+            health.current -= hit.damage;
+            if health.is_dead() {
+                // enemies face left unless flipped
+                let facing = if sprite.flip_x { Vec2::X } else { Vec2::NEG_X };
+                let headshot = is_headshot(
+                    arrow_tr.translation.xy(),
+                    enemy_tr.translation.xy(),
+                    facing,
+                    sprite.custom_size.map_or(0., |size| size.x),
+                );
+                killers.entry(hit.enemy).or_insert((hit.arrow, headshot));
+            }
+        }
+        flash_hit(&mut commands, hit.enemy);
+        commands
+            .entity(hit.enemy)
+            .insert(Knockback(vel.normalize_or_zero() * KNOCKBACK_SPEED));
+    }
+
+    for (center, radius, damage, arrow, hit_enemy) in blasts {
+        for (enemy_entity, enemy_tr, _, mut health, _) in &mut enemies {
+            let offset = enemy_tr.translation.xy() - center;
+            if enemy_entity == hit_enemy || health.is_dead() || offset.length() >= radius {
+                continue;
+            }
+            health.current -= damage;
+            if health.is_dead() {
+                killers.entry(enemy_entity).or_insert((arrow, false));
+            }
+            flash_hit(&mut commands, enemy_entity);
+            commands
+                .entity(enemy_entity)
+                .insert(Knockback(offset.normalize_or_zero() * KNOCKBACK_SPEED));
+        }
+        commands.spawn((
+            Explosion {
+                radius,
+                timer: Timer::from_seconds(0.3, TimerMode::Once),
+            },
+            Transform::from_translation(center.extend(0.)),
+            InGame,
+        ));
+    }
+
+    // in query order rather than map order, so the combo adds up the same in a replay
+    for (enemy, enemy_tr, sprite, _, value) in &enemies {
+        let Some(&(arrow, headshot)) = killers.get(&enemy) else {
+            continue;
+        };
+        let pos = enemy_tr.translation.xy();
+        // an arrow without an origin counts as shot from right where it hit
+        let origin = origins.get(arrow).map_or(pos, |origin| **origin);
+        kills.send(EnemyKilledEvent {
+            pos,
+            arrow,
+            value: **value,
+            size: sprite.custom_size.map_or(0., |size| size.x),
+            shot_distance: pos.distance(origin),
+            headshot,
+        });
+        despawns.send(DespawnEvent(enemy));
+    }
+    for arrow in resolution.spent_arrows {
+        match arrows.get(arrow) {
+            Ok((.., ArrowKind::Sticking { linger }, _)) => {
+                stick_arrow(&mut commands, arrow, *linger);
+            }
+            _ => {
+                despawns.send(DespawnEvent(arrow));
+            }
+        }
+    }
+}
+
+/// The damage an arrow deals when it hits with `speed`
+fn impact_damage(damage: f32, speed: f32) -> f32 {
+    damage * speed / FULL_DAMAGE_SPEED
+}
+
+/// An overlap between an arrow and an enemy found during a single tick
+#[derive(Clone, Copy, PartialEq, Debug)]
+struct Hit {
+    arrow: Entity,
+    enemy: Entity,
+    distance: f32,
+    damage: f32,
+}
+
+#[derive(Default, Debug)]
+struct HitResolution {
+    /// The hits that actually count, none of them on an enemy that is already dead
+    hits: Vec<Hit>,
+    /// Arrows that used up all their hits and have to be removed
+    spent_arrows: Vec<Entity>,
+}
+
+/// Decides which of the overlaps of a tick count as hits.
+///
+/// Every arrow hits as many enemies as `hits_left` allows, and no arrow is wasted
+/// on an enemy whose `health` was already used up by an earlier hit. Closer overlaps
+/// are resolved first, so an arrow touching two enemies hits the one it is closest to.
+fn resolve_hits(
+    mut candidates: Vec<Hit>,
+    hits_left: impl Fn(Entity) -> u32,
+    health: impl Fn(Entity) -> f32,
+) -> HitResolution {
+    candidates.sort_by(|a, b| {
+        a.distance
+            .total_cmp(&b.distance)
+            .then(a.arrow.cmp(&b.arrow))
+            .then(a.enemy.cmp(&b.enemy))
+    });
+
+    let mut resolution = HitResolution::default();
+    let mut remaining: HashMap<Entity, u32> = HashMap::new();
+    let mut health_left: HashMap<Entity, f32> = HashMap::new();
+    for hit in candidates {
+        let enemy_health = health_left
+            .entry(hit.enemy)
+            .or_insert_with(|| health(hit.enemy));
+        if *enemy_health <= 0. {
+            continue;
+        }
+        let left = remaining
+            .entry(hit.arrow)
+            .or_insert_with(|| hits_left(hit.arrow));
+        if *left == 0 {
+            continue;
+        }
+
+        *left -= 1;
+        if *left == 0 {
+            resolution.spent_arrows.push(hit.arrow);
+        }
+        *enemy_health -= hit.damage;
+        resolution.hits.push(hit);
+    }
+
+    resolution
 }
 
 fn update_scoreboard(
     score: Res<Scoreboard>,
+    combo: Res<Combo>,
     mut query: Query<&mut Text, With<ScoreboardUi>>,
 ) {
     let mut text = query.single_mut();
     text.sections[1].value = (**score).to_string();
+    text.sections[3].value = format!("{} (x{:.1})", combo.count, combo.multiplier());
 }
 
 fn rotate_arrows(mut arrows: Query<(&mut Transform, &Vel), With<Arrow>>) {
@@ -717,7 +2104,1147 @@ fn rotate_arrows(mut arrows: Query<(&mut Transform, &Vel), With<Arrow>>) {
 }
 
 fn despawn_entities(mut commands: Commands, mut events: EventReader<DespawnEvent>) {
-    for ev in events.read() {
-        commands.entity(**ev).despawn()
+    // several systems may want the same entity gone in one tick
+    let unique: HashSet<Entity> = events.read().map(|ev| **ev).collect();
+    for entity in unique {
+        if let Some(mut entity) = commands.get_entity(entity) {
+            entity.despawn();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::asset::AssetPlugin;
+    use bevy::ecs::bundle::Bundle;
+    use bevy::ecs::event::Events;
+    use bevy::ecs::system::RunSystemOnce;
+    use bevy::input::gamepad::GamepadButton;
+    use bevy::prelude::State;
+    use bevy::render::texture::Image;
+    use bevy::time::{Fixed, TimeUpdateStrategy};
+    use bevy::utils::Duration;
+    use bevy::MinimalPlugins;
+    use mode::GameEnd;
+    use replay::InputFrame;
+
+    /// An app without a window whose clock moves on by `step` every update, zero keeps it still.
+    /// The first update of an app only starts the clock, so it is run here
+    pub(crate) fn test_app(step: Duration) -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .insert_resource(TimeUpdateStrategy::ManualDuration(step));
+        app.update();
+        app
+    }
+
+    fn hit(arrow: u32, enemy: u32, distance: f32) -> Hit {
+        Hit {
+            arrow: Entity::from_raw(arrow),
+            enemy: Entity::from_raw(enemy),
+            distance,
+            damage: 1.,
+        }
+    }
+
+    fn collision_app() -> App {
+        let mut app = App::new();
+        app.insert_resource(Scoreboard(0))
+            .init_resource::<Combo>()
+            .init_resource::<Time>()
+            .add_event::<DespawnEvent>()
+            .add_event::<ArrowHitEvent>()
+            .add_event::<EnemyKilledEvent>()
+            .add_systems(
+                Update,
+                (check_arrow_collision, score_kills, despawn_entities).chain(),
+            );
+        app
+    }
+
+    fn enemy(x: f32, size: f32, health: f32) -> impl Bundle {
+        (
+            Enemy,
+            Health::new(health),
+            ScoreValue(1),
+            Transform::from_xyz(x, 0., 0.),
+            Sprite {
+                custom_size: Some(Vec2::splat(size)),
+                ..default()
+            },
+        )
+    }
+
+    fn arrow(x: f32, kind: ArrowKind) -> impl Bundle {
+        (
+            Arrow,
+            kind,
+            Vel(Vec2::new(FULL_DAMAGE_SPEED, 0.)),
+            Damage(1.),
+            HitEnemies::default(),
+            Transform::from_xyz(x, 0., 0.),
+        )
+    }
+
+    #[test]
+    fn arrow_hits_only_the_closest_enemy() {
+        let resolution = resolve_hits(vec![hit(0, 1, 20.), hit(0, 2, 10.)], |_| 1, |_| 1.);
+
+        assert_eq!(resolution.hits, vec![hit(0, 2, 10.)]);
+        assert_eq!(resolution.spent_arrows, vec![Entity::from_raw(0)]);
+    }
+
+    #[test]
+    fn enemy_dies_only_once() {
+        let resolution = resolve_hits(vec![hit(0, 2, 5.), hit(1, 2, 3.)], |_| 1, |_| 1.);
+
+        assert_eq!(resolution.hits, vec![hit(1, 2, 3.)]);
+        // the arrow that came too late keeps flying
+        assert_eq!(resolution.spent_arrows, vec![Entity::from_raw(1)]);
+    }
+
+    #[test]
+    fn piercing_arrow_hits_several_enemies() {
+        let candidates = vec![hit(0, 1, 1.), hit(0, 2, 2.), hit(0, 3, 3.)];
+        let resolution = resolve_hits(candidates, |_| 2, |_| 1.);
+
+        assert_eq!(resolution.hits, vec![hit(0, 1, 1.), hit(0, 2, 2.)]);
+        assert_eq!(resolution.spent_arrows, vec![Entity::from_raw(0)]);
+    }
+
+    #[test]
+    fn piercing_arrow_with_hits_left_is_not_spent() {
+        let resolution = resolve_hits(vec![hit(0, 1, 1.)], |_| 3, |_| 1.);
+
+        assert_eq!(resolution.hits.len(), 1);
+        assert!(resolution.spent_arrows.is_empty());
+    }
+
+    #[test]
+    fn weak_hits_add_up() {
+        let resolution = resolve_hits(vec![hit(0, 2, 5.), hit(1, 2, 3.)], |_| 1, |_| 2.);
+
+        assert_eq!(resolution.hits, vec![hit(1, 2, 3.), hit(0, 2, 5.)]);
+    }
+
+    #[test]
+    fn collision_scores_once_per_enemy() {
+        let mut app = collision_app();
+        app.world.spawn(enemy(0., 50., 1.));
+        app.world.spawn(enemy(10., 50., 1.));
+        app.world.spawn(arrow(5., ArrowKind::Normal));
+        app.world.spawn(arrow(6., ArrowKind::Normal));
+
+        app.update();
+
+        // two arrows, two enemies, each arrow takes down exactly one of them,
+        // small enemies are worth 1.6 each, the second one with the combo
+        assert_eq!(**app.world.resource::<Scoreboard>(), 4);
+        let mut enemies = app.world.query_filtered::<Entity, With<Enemy>>();
+        assert_eq!(enemies.iter(&app.world).count(), 0);
+        let mut arrows = app.world.query_filtered::<Entity, With<Arrow>>();
+        assert_eq!(arrows.iter(&app.world).count(), 0);
+    }
+
+    #[test]
+    fn arrow_bounces_off_the_side_it_crossed() {
+        let rect = Rect::new(0., 0., 100., 100.);
+        let bounced = reflect_off(
+            rect,
+            Vec2::new(95., 50.),
+            Vec2::new(105., 55.),
+            Vec2::new(10., 5.),
+            0.5,
+        );
+
+        assert_eq!(bounced, Some((Vec2::new(100., 55.), Vec2::new(-5., 5.))));
+    }
+
+    #[test]
+    fn arrow_entering_a_rect_does_not_bounce() {
+        let rect = Rect::new(0., 0., 100., 100.);
+        let bounced = reflect_off(
+            rect,
+            Vec2::new(-5., 50.),
+            Vec2::new(5., 50.),
+            Vec2::new(10., 0.),
+            0.5,
+        );
+
+        assert_eq!(bounced, None);
+    }
+
+    #[test]
+    fn bouncing_arrow_flies_out_of_the_enemy_area_and_bounces_off_the_playfield() {
+        let mut app = App::new();
+        let playfield = Playfield(Vec2::new(800., 600.));
+        app.init_resource::<Time>()
+            .insert_resource(playfield)
+            .insert_resource(playfield.bow_area())
+            .add_event::<DespawnEvent>()
+            .add_systems(Update, check_arrow_bounds);
+        let kind = ArrowKind::Bouncing {
+            restitution: 0.5,
+            bounces: 1,
+        };
+        // leaving the enemy area on its left side, still inside the playfield
+        let leaving = app
+            .world
+            .spawn((
+                Arrow,
+                kind,
+                Vel(Vec2::new(-100., 0.)),
+                PrevPos(Vec2::new(5., 0.)),
+                Transform::from_xyz(-5., 0., 0.),
+            ))
+            .id();
+        let hitting_the_edge = app
+            .world
+            .spawn((
+                Arrow,
+                kind,
+                Vel(Vec2::new(100., 0.)),
+                PrevPos(Vec2::new(395., 0.)),
+                Transform::from_xyz(405., 0., 0.),
+            ))
+            .id();
+
+        app.update();
+
+        assert_eq!(
+            app.world.get::<Vel>(leaving).unwrap().0,
+            Vec2::new(-100., 0.)
+        );
+        assert_eq!(
+            app.world.get::<Vel>(hitting_the_edge).unwrap().0,
+            Vec2::new(-50., 0.)
+        );
+    }
+
+    #[test]
+    fn landed_arrows_out_of_reach_fly_back_to_the_bow() {
+        let mut app = App::new();
+        let playfield = Playfield(Vec2::new(800., 600.));
+        app.init_resource::<Time>()
+            .insert_resource(playfield)
+            .insert_resource(playfield.bow_area())
+            .add_event::<DespawnEvent>()
+            .add_systems(Update, check_arrow_bounds);
+        let falling = |x: f32| {
+            (
+                arrow(x, ArrowKind::Normal),
+                Acc(Vec2::ZERO),
+                PrevPos(Vec2::new(x, -295.)),
+                ShotFrom(Vec2::ZERO),
+            )
+        };
+        let in_reach = app.world.spawn(falling(-300.)).id();
+        let out_of_reach = app.world.spawn(falling(200.)).id();
+        for entity in [in_reach, out_of_reach] {
+            app.world
+                .get_mut::<Transform>(entity)
+                .unwrap()
+                .translation
+                .y = -305.;
+        }
+
+        app.update();
+
+        for entity in [in_reach, out_of_reach] {
+            let landed = app.world.entity(entity);
+            assert!(landed.contains::<AmmoPickup>() && landed.contains::<Landed>());
+            assert!(!landed.contains::<Arrow>() && !landed.contains::<PrevPos>());
+            assert!(!landed.contains::<ArrowKind>());
+        }
+        assert!(!app.world.entity(in_reach).contains::<Homing>());
+        assert!(app.world.entity(out_of_reach).contains::<Homing>());
+    }
+
+    #[test]
+    fn only_the_latest_landed_arrows_stay() {
+        let mut app = App::new();
+        app.add_event::<DespawnEvent>()
+            .add_systems(Update, (limit_landed_arrows, despawn_entities).chain());
+        let landed: Vec<_> = (0..MAX_LANDED_ARROWS + 2)
+            .map(|at| app.world.spawn(Landed(at as f32)).id())
+            .collect();
+
+        app.update();
+
+        assert!(app.world.get_entity(landed[0]).is_none());
+        assert!(app.world.get_entity(landed[1]).is_none());
+        assert!(app.world.get_entity(landed[2]).is_some());
+    }
+
+    #[test]
+    fn pickups_near_the_bow_refill_the_quiver() {
+        let mut app = App::new();
+        let mut quiver = Quiver::new(5, QUIVER_RELOAD_TIME);
+        quiver.arrows = 2;
+        app.insert_resource(quiver)
+            .add_event::<DespawnEvent>()
+            .add_systems(Update, (collect_pickups, despawn_entities).chain());
+        app.world.spawn((Bow, Transform::default()));
+        let near = app
+            .world
+            .spawn((
+                AmmoPickup(1),
+                Transform::from_xyz(PICKUP_RADIUS / 2., 0., 0.),
+            ))
+            .id();
+        let far = app
+            .world
+            .spawn((
+                AmmoPickup(1),
+                Transform::from_xyz(PICKUP_RADIUS * 2., 0., 0.),
+            ))
+            .id();
+
+        app.update();
+
+        assert_eq!(app.world.resource::<Quiver>().arrows, 3);
+        assert!(app.world.get_entity(near).is_none());
+        assert!(app.world.get_entity(far).is_some());
+    }
+
+    #[test]
+    fn explosive_arrow_takes_down_enemies_around_the_hit() {
+        let mut app = collision_app();
+        app.world.spawn(enemy(0., 20., 1.));
+        app.world.spawn(enemy(50., 20., 1.));
+        let far = app.world.spawn(enemy(500., 20., 1.)).id();
+        app.world
+            .spawn(arrow(0., ArrowKind::Explosive { radius: 100. }));
+
+        app.update();
+
+        // 2 for the tiny enemy, the second kill of the same arrow makes 3 with the combo
+        assert_eq!(**app.world.resource::<Scoreboard>(), 5);
+        assert!(app.world.get_entity(far).is_some());
+    }
+
+    #[test]
+    fn slow_arrow_only_wounds() {
+        let mut app = collision_app();
+        let enemy = app.world.spawn(enemy(0., 50., 1.)).id();
+        app.world
+            .spawn(arrow(0., ArrowKind::Normal))
+            .insert(Vel(Vec2::new(FULL_DAMAGE_SPEED / 2., 0.)));
+
+        app.update();
+
+        assert_eq!(**app.world.resource::<Scoreboard>(), 0);
+        assert_eq!(app.world.get::<Health>(enemy).unwrap().current, 0.5);
+        assert!(app.world.get::<Knockback>(enemy).unwrap().x > 0.);
+    }
+
+    #[test]
+    fn piercing_arrow_does_not_hit_the_same_enemy_twice() {
+        let mut app = collision_app();
+        let enemy = app.world.spawn(enemy(0., 50., 3.)).id();
+        app.world.spawn(arrow(0., ArrowKind::Piercing(2)));
+
+        app.update();
+        app.update();
+
+        assert_eq!(app.world.get::<Health>(enemy).unwrap().current, 2.);
+    }
+
+    #[test]
+    fn hit_flash_gives_the_sprite_its_tint_back() {
+        let mut app = App::new();
+        app.init_resource::<Time>().add_systems(Update, flash_hits);
+        let enemy = app
+            .world
+            .spawn(Sprite {
+                color: Color::BLUE,
+                ..default()
+            })
+            .id();
+        // a second hit while flashing still remembers the tint from before the first
+        for _ in 0..2 {
+            app.world
+                .run_system_once(move |mut commands: Commands| flash_hit(&mut commands, enemy));
+            app.update();
+            assert_eq!(
+                app.world.get::<Sprite>(enemy).unwrap().color,
+                HIT_FLASH_COLOR
+            );
+        }
+
+        let mut flash = app.world.get_mut::<HitFlash>(enemy).unwrap();
+        let duration = flash.timer.duration();
+        flash.timer.tick(duration);
+        app.update();
+
+        assert_eq!(app.world.get::<Sprite>(enemy).unwrap().color, Color::BLUE);
+        assert!(app.world.get::<HitFlash>(enemy).is_none());
+    }
+
+    #[test]
+    fn arrow_selection_wraps_around() {
+        assert_eq!(cycle(0, -1, 3), 2);
+        assert_eq!(cycle(2, 1, 3), 0);
+        assert_eq!(cycle(1, 1, 3), 2);
+    }
+
+    #[test]
+    fn shrinking_the_catalog_keeps_the_selection_inside() {
+        let mut app = App::new();
+        app.init_resource::<ArrowCatalog>()
+            .insert_resource(SelectedArrow(3))
+            .add_systems(Update, fit_selected_arrow);
+        app.world.resource_mut::<ArrowCatalog>().truncate(2);
+
+        app.update();
+
+        assert_eq!(**app.world.resource::<SelectedArrow>(), 1);
+    }
+
+    #[test]
+    fn empty_quiver_blocks_the_shot() {
+        let mut quiver = Quiver::new(1, 1.);
+
+        assert!(quiver.take());
+        assert!(!quiver.take());
+        assert!(quiver.dry_fire > 0.);
+
+        quiver.refill(5);
+        assert_eq!(quiver.arrows, 1);
+    }
+
+    #[test]
+    fn duplicate_despawns_are_ignored() {
+        let mut app = App::new();
+        app.add_event::<DespawnEvent>()
+            .add_systems(Update, despawn_entities);
+
+        let entity = app.world.spawn_empty().id();
+        app.world.send_event(DespawnEvent(entity));
+        app.world.send_event(DespawnEvent(entity));
+        app.update();
+
+        assert!(app.world.get_entity(entity).is_none());
+    }
+
+    /// The shooting part of the game without rendering, a fixed tick per update
+    fn replay_app(recording: Recording) -> App {
+        let replay = Replay::new(recording);
+        // the size of the window the tests used to play in
+        let playfield = Playfield(replay.playfield().unwrap_or(Vec2::new(800., 600.)));
+        let mut app = test_app(Time::<Fixed>::default().timestep());
+        app.add_plugins(AssetPlugin::default())
+            .init_asset::<Image>()
+            .init_asset::<TextureAtlasLayout>()
+            .init_asset::<AnimationClips>()
+            .init_asset::<Waves>()
+            .init_asset::<EnemyArchetypes>()
+            .insert_state(GameState::Playing)
+            .insert_resource(GameRng::new(replay.seed()))
+            .insert_resource(replay.mode())
+            .insert_resource(replay.fatigue())
+            .insert_resource(replay.controls())
+            .insert_resource(replay.pull_mode())
+            .insert_resource(Run::new(replay.mode()))
+            .insert_resource(replay)
+            .insert_resource(Mouse(Vec2::ZERO))
+            .init_resource::<MoveInput>()
+            .init_resource::<DrawButton>()
+            .insert_resource(Scoreboard(0))
+            .init_resource::<Combo>()
+            .init_resource::<Analytics>()
+            .insert_resource(G(18.))
+            .init_resource::<ArrowCatalog>()
+            .init_resource::<BowAtlas>()
+            .insert_resource(SelectedArrow(0))
+            .insert_resource(Quiver::new(QUIVER_CAPACITY, QUIVER_RELOAD_TIME))
+            .init_resource::<WaveState>()
+            .insert_resource(playfield)
+            .insert_resource(playfield.bow_area())
+            .insert_resource(playfield.enemy_area())
+            .insert_resource(PathFinder::MinLength(MinLength(
+                playfield.enemy_area().rect.width() / 2.,
+            )));
+        add_simulation(&mut app);
+
+        let waves: Waves = ron::from_str(include_str!("../assets/default.waves.ron")).unwrap();
+        let waves = app.world.resource_mut::<Assets<Waves>>().add(waves);
+        let archetypes: EnemyArchetypes =
+            ron::from_str(include_str!("../assets/enemies.archetypes.ron")).unwrap();
+        let archetypes = app
+            .world
+            .resource_mut::<Assets<EnemyArchetypes>>()
+            .add(archetypes);
+        app.insert_resource(WavesHandle(waves))
+            .insert_resource(EnemyArchetypesHandle(archetypes));
+        let clips = app.world.resource_mut::<Assets<AnimationClips>>().add(
+            ron::from_str::<AnimationClips>(include_str!("../assets/bow/bow.clips.ron")).unwrap(),
+        );
+        app.world.spawn((
+            Bow,
+            Fixed(false),
+            BowPullTime::default(),
+            DragAnchor::default(),
+            Tired::default(),
+            BowState::default(),
+            Animator::new(clips, "idle"),
+            Handle::<Image>::default(),
+            TextureAtlas::default(),
+            Transform::default(),
+        ));
+        app.world.spawn(enemy(100., 200., 0.5));
+        app
+    }
+
+    /// Moves the bow to `(-300, 0)`, draws it for a second towards `aim`, and lets go
+    fn shot(aim: Vec2) -> Vec<InputFrame> {
+        let frame = |mouse: Vec2, pressed| InputFrame {
+            mouse: (mouse.x, mouse.y),
+            pressed,
+            ..default()
+        };
+        let bow = Vec2::new(-300., 0.);
+        let mut frames = vec![frame(bow, false); 5];
+        frames.extend(vec![frame(bow - aim, true); 64]);
+        frames.extend(vec![frame(bow - aim, false); 120]);
+        frames
+    }
+
+    fn play(recording: Recording) -> u32 {
+        let ticks = recording.frames.len();
+        let mut app = replay_app(recording);
+        for _ in 0..ticks {
+            app.update();
+        }
+        assert!(app.world.resource::<Replay>().is_finished());
+        **app.world.resource::<Scoreboard>()
+    }
+
+    #[test]
+    fn replayed_shot_scores() {
+        let recording = Recording {
+            seed: 1,
+            frames: shot(Vec2::new(1., 0.1)),
+            ..default()
+        };
+        assert_eq!(play(recording), 1);
+    }
+
+    #[test]
+    fn replayed_miss_does_not_score() {
+        let recording = Recording {
+            seed: 1,
+            frames: shot(Vec2::new(-1., 0.)),
+            ..default()
+        };
+        assert_eq!(play(recording), 0);
+    }
+
+    #[test]
+    fn replayed_waves_spawn_enemies_and_get_them_shot_the_same_way() {
+        // a fan of shots while the first wave flies in
+        let frames: Vec<_> = (0..8)
+            .flat_map(|i| shot(Vec2::new(1., 0.1 * i as f32 - 0.2)))
+            .collect();
+        let replay = |frames: Vec<InputFrame>| {
+            let ticks = frames.len();
+            let mut app = replay_app(Recording {
+                seed: 3,
+                frames,
+                ..default()
+            });
+            for _ in 0..ticks {
+                app.update();
+            }
+            let mut enemies = app
+                .world
+                .query_filtered::<&Transform, (With<Enemy>, With<Path>)>();
+            let positions: Vec<_> = enemies
+                .iter(&app.world)
+                .map(|tr| tr.translation.xy())
+                .collect();
+            let analytics = app.world.resource::<Analytics>();
+            (
+                app.world.resource::<WaveState>().spawned,
+                analytics.shots,
+                analytics.hits,
+                **app.world.resource::<Scoreboard>(),
+                positions,
+            )
+        };
+
+        let first = replay(frames.clone());
+        let (spawned, shots, hits, ..) = first;
+        assert!(spawned > 1);
+        assert_eq!(shots, 8);
+        assert!(hits > 1);
+        assert_eq!(replay(frames), first);
+    }
+
+    /// Plays `recording` until the first arrow is shot and returns the velocity it is shot with
+    fn first_shot(app: &mut App, recording: &Recording) -> Option<Vec2> {
+        for _ in 0..recording.frames.len() {
+            app.update();
+            let shots = app.world.resource::<Events<ArrowShotEvent>>();
+            if let Some(shot) = shots.iter_current_update_events().next() {
+                return Some(shot.velocity);
+            }
+        }
+        None
+    }
+
+    #[test]
+    fn enemies_avoid_the_ray_the_arrows_fly_along() {
+        let recording = Recording {
+            seed: 1,
+            frames: shot(Vec2::new(1., 0.3)),
+            ..default()
+        };
+        let mut app = replay_app(recording.clone());
+        let velocity = first_shot(&mut app, &recording).unwrap();
+
+        let mut bow = app.world.query_filtered::<&Transform, With<Bow>>();
+        let (_, aim) = PathContext::new(None, bow.get_single(&app.world).ok())
+            .bow
+            .unwrap();
+        assert!((aim - velocity.normalize()).length() < 1e-4);
+    }
+
+    #[test]
+    fn arrows_fly_away_from_a_pulling_cursor_and_towards_an_aiming_one() {
+        let recording = Recording {
+            seed: 1,
+            frames: shot(Vec2::new(1., 0.3)),
+            ..default()
+        };
+        let velocity = first_shot(&mut replay_app(recording.clone()), &recording).unwrap();
+        assert!((velocity.normalize() - Vec2::new(1., 0.3).normalize()).length() < 1e-4);
+
+        let aim = Vec2::new(0., 200.);
+        let frame = |pressed| InputFrame {
+            mouse: (aim.x, aim.y),
+            pressed,
+            ..default()
+        };
+        // the bow rests at (-200, 0), the right edge of its area
+        let mut frames = vec![frame(true); 64];
+        frames.push(frame(false));
+        let recording = Recording {
+            seed: 1,
+            controls: ControlScheme::TWIN,
+            frames,
+            ..default()
+        };
+        let velocity = first_shot(&mut replay_app(recording.clone()), &recording).unwrap();
+        assert!((velocity.normalize() - Vec2::ONE.normalize()).length() < 1e-4);
+    }
+
+    #[test]
+    fn letting_go_with_the_cursor_on_the_bow_keeps_the_arrow() {
+        let bow = Vec2::new(-300., 0.);
+        let frame = |pressed| InputFrame {
+            mouse: (bow.x, bow.y),
+            pressed,
+            ..default()
+        };
+        let mut frames = vec![frame(false); 5];
+        frames.extend(vec![frame(true); 64]);
+        frames.push(frame(false));
+        let recording = Recording {
+            seed: 1,
+            frames,
+            ..default()
+        };
+        let mut app = replay_app(recording.clone());
+
+        assert_eq!(first_shot(&mut app, &recording), None);
+        assert_eq!(app.world.resource::<Run>().shots, 0);
+        assert_eq!(app.world.resource::<Quiver>().arrows, QUIVER_CAPACITY);
+    }
+
+    #[test]
+    fn cancelled_draw_does_not_shoot() {
+        let mut frames = shot(Vec2::new(1., 0.1));
+        // cancelled right before letting go, the cancel holds until the button is let go
+        for frame in &mut frames[69..79] {
+            frame.cancelled = true;
+        }
+        let ticks = frames.len();
+        let mut app = replay_app(Recording {
+            seed: 1,
+            frames,
+            ..default()
+        });
+        for _ in 0..ticks {
+            app.update();
+        }
+
+        assert_eq!(app.world.resource::<Run>().shots, 0);
+        assert_eq!(app.world.resource::<Quiver>().arrows, QUIVER_CAPACITY);
+        let mut bow = app
+            .world
+            .query_filtered::<(&BowPullTime, &TextureAtlas, &super::Fixed), With<Bow>>();
+        let (pull_time, atlas, fixed) = bow.single(&app.world);
+        assert_eq!(**pull_time, 0.);
+        assert_eq!(atlas.index, 0);
+        assert!(!**fixed);
+    }
+
+    #[test]
+    fn twin_controls_move_the_bow_and_aim_at_the_cursor() {
+        let aim = Vec2::new(0., 300.);
+        let frame = |movement: Vec2, pressed| InputFrame {
+            mouse: (aim.x, aim.y),
+            movement: (movement.x, movement.y),
+            pressed,
+            ..default()
+        };
+        // half a second up with the bow drawn, then half a second down without
+        let mut frames = vec![frame(Vec2::Y, true); 32];
+        frames.extend(vec![frame(Vec2::NEG_Y, false); 32]);
+        let ticks = frames.len();
+        let mut app = replay_app(Recording {
+            seed: 1,
+            controls: ControlScheme::TWIN,
+            frames,
+            ..default()
+        });
+        for _ in 0..ticks {
+            app.update();
+        }
+
+        let mut bow = app.world.query_filtered::<&Transform, With<Bow>>();
+        let tr = bow.single(&app.world);
+        // drawn it only moved at the draw speed, the cursor didn't pull it along
+        assert_eq!(tr.translation.x, -200.);
+        assert!((tr.translation.y - (50. - 150.)).abs() < 0.1);
+        // and it kept aiming at the cursor after letting go
+        let facing = (tr.rotation * Vec3::X).xy();
+        assert!((facing - (aim - tr.translation.xy()).normalize()).length() < 1e-4);
+        assert_eq!(app.world.resource::<Run>().shots, 1);
+    }
+
+    #[test]
+    fn cursor_on_the_bow_keeps_the_aim() {
+        let frame = |mouse: Vec2| InputFrame {
+            mouse: (mouse.x, mouse.y),
+            ..default()
+        };
+        // the bow rests at (-200, 0), the right edge of its area
+        let mut frames = vec![frame(Vec2::new(0., 200.)); 4];
+        frames.extend(vec![frame(Vec2::new(-200., 0.)); 4]);
+        let mut app = replay_app(Recording {
+            seed: 1,
+            controls: ControlScheme::TWIN,
+            frames,
+            ..default()
+        });
+        let mut bow = app.world.query_filtered::<&Transform, With<Bow>>();
+        for _ in 0..4 {
+            app.update();
+        }
+        let aimed = bow.single(&app.world).rotation;
+        for _ in 0..4 {
+            app.update();
+        }
+
+        let tr = bow.single(&app.world);
+        assert_eq!(tr.translation.xy(), Vec2::new(-200., 0.));
+        assert_eq!(tr.rotation, aimed);
+        assert!(aimed.is_finite());
+    }
+
+    #[test]
+    fn dragging_pulls_as_far_as_the_drag() {
+        let frame = |x: f32, pressed| InputFrame {
+            mouse: (x, 0.),
+            pressed,
+            ..default()
+        };
+        // a quick drag half as far as a full draw
+        let frames = vec![
+            frame(-300., false),
+            frame(-300., true),
+            frame(-350., true),
+            frame(-425., true),
+        ];
+        let ticks = frames.len();
+        let mut app = replay_app(Recording {
+            seed: 1,
+            pull_mode: PullMode::DRAG,
+            frames,
+            ..default()
+        });
+        for _ in 0..ticks {
+            app.update();
+        }
+
+        let mut bow = app.world.query_filtered::<&BowPullTime, With<Bow>>();
+        assert_eq!(**bow.single(&app.world), BOW_FULL_PULL_TIME / 2.);
+    }
+
+    #[test]
+    fn bow_cannot_be_drawn_again_while_recovering() {
+        let frame = |pressed| InputFrame {
+            mouse: (-300., 0.),
+            pressed,
+            ..default()
+        };
+        let mut frames = vec![frame(true); 64];
+        frames.push(frame(false));
+        frames.extend(vec![frame(true); 40]);
+        let mut app = replay_app(Recording {
+            seed: 1,
+            frames,
+            ..default()
+        });
+        let mut bow = app
+            .world
+            .query_filtered::<(&BowState, &super::Fixed), With<Bow>>();
+        for _ in 0..75 {
+            app.update();
+        }
+        let (state, fixed) = bow.single(&app.world);
+        assert!(matches!(state, BowState::Recovering { .. }));
+        assert!(!**fixed);
+        // the release played and went back to rest
+        let mut animator = app.world.query_filtered::<&Animator, With<Bow>>();
+        assert_eq!(animator.single(&app.world).clip(), "idle");
+
+        // holding on draws the bow once it recovered
+        for _ in 0..30 {
+            app.update();
+        }
+        let (state, fixed) = bow.single(&app.world);
+        assert_eq!(*state, BowState::Pulling);
+        assert!(**fixed);
+        assert_eq!(app.world.resource::<Run>().shots, 1);
+    }
+
+    #[test]
+    fn release_lasts_as_long_as_its_clip() {
+        let frame = |pressed| InputFrame {
+            mouse: (-300., 0.),
+            pressed,
+            ..default()
+        };
+        let mut frames = vec![frame(true); 64];
+        frames.extend(vec![frame(false); 100]);
+        let mut app = replay_app(Recording {
+            seed: 1,
+            frames,
+            ..default()
+        });
+        let mut bow = app
+            .world
+            .query_filtered::<(&Animator, &BowState), With<Bow>>();
+        let clips = bow.single(&app.world).0.clips.clone();
+        // three frames of half a second each
+        let mut library = app.world.resource_mut::<Assets<AnimationClips>>();
+        library
+            .get_mut(&clips)
+            .unwrap()
+            .0
+            .get_mut("release")
+            .unwrap()
+            .durations = vec![0.5];
+
+        // a second after letting go
+        for _ in 0..128 {
+            app.update();
+        }
+        assert!(matches!(
+            bow.single(&app.world).1,
+            BowState::Releasing { .. }
+        ));
+        for _ in 0..40 {
+            app.update();
+        }
+        assert!(matches!(
+            bow.single(&app.world).1,
+            BowState::Recovering { .. }
+        ));
+    }
+
+    #[test]
+    fn recording_survives_a_round_trip() {
+        let recording = Recording {
+            seed: 42,
+            frames: shot(Vec2::new(1., 0.1)),
+            ..default()
+        };
+        let path = std::env::temp_dir().join("bevy-bow-round-trip.replay.ron");
+        recording.save(&path).unwrap();
+        let loaded = Recording::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded, recording);
+        assert_eq!(play(loaded), 1);
+    }
+
+    fn state_app() -> App {
+        let mut app = test_app(Duration::ZERO);
+        app.init_state::<GameState>()
+            .init_resource::<ButtonInput<KeyCode>>()
+            .init_resource::<ButtonInput<MouseButton>>()
+            .init_resource::<ButtonInput<GamepadButton>>()
+            .insert_resource(Scoreboard(0))
+            .init_resource::<GameMode>()
+            .init_resource::<Run>()
+            .init_resource::<WaveState>()
+            .init_resource::<Combo>()
+            .init_resource::<StatsPath>()
+            .init_resource::<Stats>()
+            .init_resource::<NewHighScore>()
+            .add_systems(Update, navigate)
+            .add_systems(
+                OnEnter(GameState::GameOver),
+                (record_game, show_game_over).chain(),
+            )
+            .add_systems(
+                OnExit(GameState::GameOver),
+                (despawn_all::<GameOverUi>, despawn_all::<InGame>),
+            );
+        app
+    }
+
+    fn press(app: &mut App, key: KeyCode) {
+        app.world.resource_mut::<ButtonInput<KeyCode>>().press(key);
+        app.update();
+        let mut keys = app.world.resource_mut::<ButtonInput<KeyCode>>();
+        keys.release(key);
+        keys.clear();
+        // the transition happens at the start of the next update
+        app.update();
+    }
+
+    fn state(app: &App) -> GameState {
+        *app.world.resource::<State<GameState>>().get()
+    }
+
+    #[test]
+    fn game_goes_through_pause_to_game_over_and_back_to_the_menu() {
+        let mut app = state_app();
+        app.update();
+        assert_eq!(state(&app), GameState::Menu);
+
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(state(&app), GameState::Playing);
+        let enemy = app.world.spawn((Enemy, InGame)).id();
+
+        press(&mut app, KeyCode::Escape);
+        assert_eq!(state(&app), GameState::Paused);
+        press(&mut app, KeyCode::KeyP);
+        assert_eq!(state(&app), GameState::Playing);
+        // giving up only works while paused
+        press(&mut app, KeyCode::KeyQ);
+        assert_eq!(state(&app), GameState::Playing);
+
+        press(&mut app, KeyCode::KeyP);
+        press(&mut app, KeyCode::KeyQ);
+        assert_eq!(state(&app), GameState::GameOver);
+        assert!(app.world.get_entity(enemy).is_some());
+        assert_eq!(app.world.resource::<Stats>().lifetime.games, 1);
+        assert_eq!(app.world.resource::<NewHighScore>().0, Some(0));
+
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(state(&app), GameState::Menu);
+        assert!(app.world.get_entity(enemy).is_none());
+    }
+
+    fn mode_app(mode: GameMode) -> App {
+        let mut app = test_app(Duration::from_millis(250));
+        app.insert_state(GameState::Playing)
+            .insert_resource(mode)
+            .insert_resource(Run::new(mode))
+            .insert_resource(Quiver::new(2, QUIVER_RELOAD_TIME))
+            .insert_resource(BowArea(Area::new(
+                Vec2::new(-400., 300.),
+                Vec2::new(-200., -300.),
+            )))
+            .add_event::<DespawnEvent>()
+            .add_event::<ArrowHitEvent>()
+            .add_event::<EnemyKilledEvent>()
+            .add_event::<EnemyEscapedEvent>()
+            .add_systems(
+                Update,
+                (escape_enemies, track_run, despawn_entities).chain(),
+            );
+        app
+    }
+
+    fn flying_enemy(x: f32, flight_time: f32) -> impl Bundle {
+        (enemy(x, 50., 1.), movement::FlightTime(flight_time))
+    }
+
+    #[test]
+    fn escaping_enemies_cost_lives_until_the_game_is_over() {
+        let mut app = mode_app(GameMode::Lives(2));
+        // one reached the bow, one flew around for too long, one is still around
+        app.world.spawn(flying_enemy(-300., 0.));
+        app.world.spawn(flying_enemy(100., 100.));
+        app.world.spawn(flying_enemy(100., 0.));
+        app.update();
+        app.update();
+
+        let run = app.world.resource::<Run>();
+        assert_eq!(run.lives, 0);
+        assert_eq!(run.escaped, 2);
+        assert_eq!(run.end, Some(GameEnd::OutOfLives));
+        assert_eq!(state(&app), GameState::GameOver);
+    }
+
+    #[test]
+    fn timed_game_ends_when_the_time_is_up() {
+        let mut app = mode_app(GameMode::Timed(1.));
+        for _ in 0..3 {
+            app.update();
+        }
+        assert_eq!(state(&app), GameState::Playing);
+        app.update();
+        app.update();
+        assert_eq!(app.world.resource::<Run>().end, Some(GameEnd::TimeUp));
+        assert_eq!(state(&app), GameState::GameOver);
+    }
+
+    #[test]
+    fn limited_arrows_end_once_the_last_arrow_is_gone() {
+        let mut app = mode_app(GameMode::LimitedArrows(2));
+        app.world.resource_mut::<Quiver>().arrows = 0;
+        let arrow = app.world.spawn(Arrow).id();
+        app.update();
+        app.update();
+        assert_eq!(state(&app), GameState::Playing);
+
+        app.world.despawn(arrow);
+        app.update();
+        app.update();
+        assert_eq!(app.world.resource::<Run>().end, Some(GameEnd::OutOfArrows));
+        assert_eq!(state(&app), GameState::GameOver);
+    }
+
+    #[test]
+    fn arrows_split_off_one_shot_count_one_hit() {
+        let mut app = collision_app();
+        app.world.spawn(enemy(0., 50., 1.));
+        app.world.spawn(enemy(500., 50., 1.));
+        // one arrow that left the bow and one that split off it
+        let shot = app.world.spawn(arrow(0., ArrowKind::Normal)).id();
+        app.world.entity_mut(shot).insert(Shot(shot));
+        app.world
+            .spawn((arrow(500., ArrowKind::Normal), Shot(shot)));
+        // and an arrow of another shot
+        app.world.spawn(enemy(1000., 50., 1.));
+        app.world.spawn(arrow(1000., ArrowKind::Normal));
+
+        app.update();
+
+        let events = app.world.resource::<Events<ArrowHitEvent>>();
+        let first: Vec<bool> = events
+            .get_reader()
+            .read(events)
+            .map(|hit| hit.first)
+            .collect();
+        assert_eq!(first.len(), 3);
+        assert_eq!(first.iter().filter(|first| **first).count(), 2);
+    }
+
+    #[test]
+    fn hitting_the_front_of_an_enemy_is_a_headshot() {
+        let mut app = collision_app();
+        app.world.spawn(enemy(0., 80., 1.));
+        // enemies face left, so the head is on the negative side
+        app.world.spawn(arrow(-30., ArrowKind::Normal));
+        app.world.spawn(enemy(500., 80., 1.));
+        app.world.spawn(arrow(530., ArrowKind::Normal));
+
+        app.update();
+
+        let kills: Vec<bool> = app
+            .world
+            .resource::<Events<EnemyKilledEvent>>()
+            .get_reader()
+            .read(app.world.resource::<Events<EnemyKilledEvent>>())
+            .map(|kill| kill.headshot)
+            .collect();
+        assert_eq!(kills.len(), 2);
+        assert_eq!(kills.iter().filter(|headshot| **headshot).count(), 1);
+    }
+
+    #[test]
+    fn analytics_average_shots_and_hits() {
+        let mut app = App::new();
+        app.insert_resource(EnemyArea(Area::new(
+            Vec2::new(0., 300.),
+            Vec2::new(400., -300.),
+        )))
+        .init_resource::<Analytics>()
+        .add_event::<ArrowShotEvent>()
+        .add_event::<ArrowHitEvent>()
+        .add_event::<EnemyKilledEvent>()
+        .add_systems(Update, collect_analytics);
+        let catalog = ArrowCatalog::default();
+        for pull_time in [Some(0.5), Some(1.), None] {
+            app.world.send_event(ArrowShotEvent {
+                pos: Vec2::ZERO,
+                origin: Vec2::ZERO,
+                pull_time,
+                shot: None,
+                angle: Quat::IDENTITY,
+                velocity: Vec2::X,
+                arrow: catalog[0].clone(),
+            });
+        }
+        for (first, shot_distance) in [(true, 300.), (false, 350.)] {
+            app.world.send_event(ArrowHitEvent {
+                first,
+                shot_distance,
+            });
+        }
+
+        app.update();
+
+        let analytics = app.world.resource::<Analytics>();
+        assert_eq!(analytics.shots, 2);
+        assert_eq!(analytics.hits, 1);
+        assert_eq!(analytics.hit_rate(), 0.5);
+        assert_eq!(analytics.average_pull_time(), 0.75);
+        assert_eq!(analytics.average_hit_distance(), 300.);
+    }
+
+    #[test]
+    fn split_arrows_hit_once_for_their_shot() {
+        let mut app = collision_app();
+        app.insert_resource(EnemyArea(Area::new(
+            Vec2::new(-100., 300.),
+            Vec2::new(600., -300.),
+        )))
+        .init_resource::<Analytics>()
+        .add_event::<ArrowShotEvent>()
+        .add_systems(Update, collect_analytics.after(check_arrow_collision));
+        app.world.send_event(ArrowShotEvent {
+            pos: Vec2::ZERO,
+            origin: Vec2::ZERO,
+            pull_time: Some(1.),
+            shot: None,
+            angle: Quat::IDENTITY,
+            velocity: Vec2::X,
+            arrow: ArrowCatalog::default()[0].clone(),
+        });
+        // the shot split in two and both arrows hit
+        let shot = app.world.spawn_empty().id();
+        app.world.spawn(enemy(0., 50., 1.));
+        app.world.spawn(enemy(500., 50., 1.));
+        app.world.spawn((arrow(0., ArrowKind::Normal), Shot(shot)));
+        app.world
+            .spawn((arrow(500., ArrowKind::Normal), Shot(shot)));
+
+        app.update();
+
+        let analytics = app.world.resource::<Analytics>();
+        assert_eq!(analytics.shots, 1);
+        assert_eq!(analytics.hits, 1);
+        assert_eq!(analytics.hit_rate(), 1.);
     }
 }
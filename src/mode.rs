@@ -0,0 +1,250 @@
+use bevy::input::{keyboard::KeyCode, ButtonInput};
+use bevy::math::Vec3Swizzles;
+use bevy::prelude::{
+    Component, Entity, Event, EventReader, EventWriter, NextState, Query, Reflect, Res, ResMut,
+    Resource, Transform, With,
+};
+use bevy::text::Text;
+use bevy::time::Time;
+use serde::{Deserialize, Serialize};
+
+use crate::movement::FlightTime;
+use crate::state::GameState;
+
+use super::{Arrow, ArrowHitEvent, BowArea, DespawnEvent, Enemy, EnemyKilledEvent, Health, Quiver};
+
+/// Seconds an enemy stays before it escapes
+const ESCAPE_TIME: f32 = 20.;
+
+/// How a game is played and how it ends
+#[derive(Resource, Reflect, Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum GameMode {
+    /// Escaping enemies and enemies reaching the bow cost a life, the game ends without lives
+    Lives(u32),
+    /// Score as much as possible in the given seconds
+    Timed(f32),
+    /// Only the given arrows, no reloading and no pickups
+    LimitedArrows(u32),
+}
+
+impl Default for GameMode {
+    fn default() -> Self {
+        GameMode::Lives(3)
+    }
+}
+
+impl GameMode {
+    /// The modes to choose from in the menu
+    pub const ALL: [GameMode; 3] = [
+        GameMode::Lives(3),
+        GameMode::Timed(90.),
+        GameMode::LimitedArrows(30),
+    ];
+
+    pub fn describe(&self) -> String {
+        match self {
+            GameMode::Lives(lives) => format!("{lives} lives"),
+            GameMode::Timed(seconds) => format!("{seconds:.0} seconds"),
+            GameMode::LimitedArrows(arrows) => format!("{arrows} arrows"),
+        }
+    }
+
+    /// Whether the quiver reloads and arrows can be picked up again
+    pub fn refills_quiver(&self) -> bool {
+        !matches!(self, GameMode::LimitedArrows(_))
+    }
+}
+
+/// Run condition for the systems that put arrows back into the quiver
+pub fn refills_quiver(mode: Res<GameMode>) -> bool {
+    mode.refills_quiver()
+}
+
+/// Why a game ended
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum GameEnd {
+    OutOfLives,
+    TimeUp,
+    OutOfArrows,
+    GaveUp,
+}
+
+impl GameEnd {
+    pub fn describe(&self) -> &'static str {
+        match self {
+            GameEnd::OutOfLives => "Out of lives",
+            GameEnd::TimeUp => "Time's up",
+            GameEnd::OutOfArrows => "Out of arrows",
+            GameEnd::GaveUp => "Gave up",
+        }
+    }
+}
+
+/// The running game, what the results summary is made of
+#[derive(Resource, Default, Clone, PartialEq, Debug)]
+pub struct Run {
+    pub lives: u32,
+    /// Seconds played
+    pub time: f32,
+    pub shots: u32,
+    /// Shots that hit at least one enemy
+    pub hits: u32,
+    pub kills: u32,
+    /// The furthest an arrow flew before hitting something
+    pub longest_shot: f32,
+    pub escaped: u32,
+    pub end: Option<GameEnd>,
+}
+
+impl Run {
+    pub fn new(mode: GameMode) -> Self {
+        Run {
+            lives: match mode {
+                GameMode::Lives(lives) => lives,
+                _ => 0,
+            },
+            ..Run::default()
+        }
+    }
+}
+
+/// An enemy that got away, either by time or by reaching the bow
+#[derive(Event)]
+pub struct EnemyEscapedEvent;
+
+#[derive(Component)]
+pub struct ModeUi;
+
+#[derive(Component)]
+pub struct ModeChoiceUi;
+
+/// Picks the mode with the number keys in the menu
+pub fn choose_mode(keys: Res<ButtonInput<KeyCode>>, mut mode: ResMut<GameMode>) {
+    const DIGITS: [KeyCode; 3] = [KeyCode::Digit1, KeyCode::Digit2, KeyCode::Digit3];
+    for (key, choice) in DIGITS.iter().zip(GameMode::ALL) {
+        if keys.just_pressed(*key) {
+            *mode = choice;
+        }
+    }
+}
+
+pub fn update_mode_choice(mode: Res<GameMode>, mut query: Query<&mut Text, With<ModeChoiceUi>>) {
+    for mut text in &mut query {
+        let choices = text.sections.iter_mut().zip(GameMode::ALL).enumerate();
+        for (index, (section, choice)) in choices {
+            // the chosen mode may have been tweaked in the inspector
+            let chosen = std::mem::discriminant(&choice) == std::mem::discriminant(&*mode);
+            let marker = if chosen { ">" } else { " " };
+            section.value = format!("{marker} {}: {}\n", index + 1, choice.describe());
+        }
+    }
+}
+
+pub fn escape_enemies(
+    bow_area: Res<BowArea>,
+    enemies: Query<(Entity, &Transform, &FlightTime, &Health), With<Enemy>>,
+    mut despawns: EventWriter<DespawnEvent>,
+    mut escapes: EventWriter<EnemyEscapedEvent>,
+) {
+    for (entity, tr, flight_time, health) in &enemies {
+        if health.is_dead() {
+            continue;
+        }
+        if **flight_time >= ESCAPE_TIME || bow_area.rect.contains(tr.translation.xy()) {
+            despawns.send(DespawnEvent(entity));
+            escapes.send(EnemyEscapedEvent);
+        }
+    }
+}
+
+/// Keeps track of the run and ends the game once the mode says so
+#[allow(clippy::too_many_arguments)]
+pub fn track_run(
+    time: Res<Time>,
+    mode: Res<GameMode>,
+    quiver: Res<Quiver>,
+    arrows: Query<(), With<Arrow>>,
+    mut run: ResMut<Run>,
+    mut hits: EventReader<ArrowHitEvent>,
+    mut kills: EventReader<EnemyKilledEvent>,
+    mut escapes: EventReader<EnemyEscapedEvent>,
+    mut next: ResMut<NextState<GameState>>,
+) {
+    run.time += time.delta_seconds();
+    for hit in hits.read() {
+        run.hits += u32::from(hit.first);
+        run.longest_shot = run.longest_shot.max(hit.shot_distance);
+    }
+    run.kills += kills.read().count() as u32;
+    let escaped = escapes.read().count() as u32;
+    run.escaped += escaped;
+
+    let end = match *mode {
+        GameMode::Lives(_) => {
+            run.lives = run.lives.saturating_sub(escaped);
+            (run.lives == 0).then_some(GameEnd::OutOfLives)
+        }
+        GameMode::Timed(seconds) => (run.time >= seconds).then_some(GameEnd::TimeUp),
+        // the last arrows still get to land
+        GameMode::LimitedArrows(_) => {
+            (quiver.arrows == 0 && arrows.is_empty()).then_some(GameEnd::OutOfArrows)
+        }
+    };
+    if end.is_some() && run.end.is_none() {
+        run.end = end;
+        next.set(GameState::GameOver);
+    }
+}
+
+pub fn update_mode_ui(
+    mode: Res<GameMode>,
+    run: Res<Run>,
+    quiver: Res<Quiver>,
+    mut query: Query<&mut Text, With<ModeUi>>,
+) {
+    let mut text = query.single_mut();
+    let (label, value) = match *mode {
+        GameMode::Lives(_) => ("Lives: ", run.lives.to_string()),
+        GameMode::Timed(seconds) => (
+            "Time: ",
+            format!("{:.0}s", (seconds - run.time).max(0.).ceil()),
+        ),
+        GameMode::LimitedArrows(_) => ("Arrows left: ", quiver.arrows.to_string()),
+    };
+    text.sections[0].value = label.into();
+    text.sections[1].value = value;
+}
+
+/// The lines of the results summary
+pub fn summary(mode: GameMode, run: &Run, score: u32, wave: usize) -> Vec<String> {
+    let end = run.end.unwrap_or(GameEnd::GaveUp);
+    vec![
+        format!("{} ({})", end.describe(), mode.describe()),
+        format!("Score: {score}"),
+        format!("Wave: {}", wave + 1),
+        format!("Time: {:.0}s", run.time),
+        format!(
+            "Shots: {}  Kills: {}  Escaped: {}",
+            run.shots, run.kills, run.escaped
+        ),
+        format!("Hits: {}  Longest shot: {:.0}", run.hits, run.longest_shot),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summary_falls_back_to_giving_up() {
+        let run = Run {
+            shots: 4,
+            kills: 3,
+            ..Run::new(GameMode::Timed(60.))
+        };
+        let lines = summary(GameMode::Timed(60.), &run, 12, 2);
+        assert_eq!(lines[0], "Gave up (60 seconds)");
+        assert_eq!(lines[1], "Score: 12");
+        assert_eq!(lines[2], "Wave: 3");
+    }
+}
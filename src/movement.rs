@@ -0,0 +1,345 @@
+use std::f32::consts::TAU;
+
+use bevy::ecs::query::Has;
+use bevy::math::{Vec2, Vec3Swizzles};
+use bevy::prelude::{
+    Commands, Component, Deref, DerefMut, Entity, Query, Res, ResMut, Transform, With, Without,
+};
+use bevy::time::Time;
+use bevy::utils::HashMap;
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::path_finding::{PathContext, PathFinder, PathFindingStrategy};
+use crate::rng::GameRng;
+
+use super::{pick, Bow, EndsAt, Enemy, EnemyArea, Path, Side, Speed, Vel};
+
+/// How much faster an enemy flies while diving
+const DIVE_SPEEDUP: f32 = 2.;
+/// Enemies closer than this to each other are in the same part of their flock
+const FLOCK_RADIUS: f32 = 100.;
+/// A flock picks a new path once one of its members is this close to the end of the old one
+const FLOCK_ARRIVAL: f32 = 40.;
+const SEPARATION_WEIGHT: f32 = 20000.;
+const ALIGNMENT_WEIGHT: f32 = 1.;
+const COHESION_WEIGHT: f32 = 1.;
+const SEEK_WEIGHT: f32 = 2.;
+
+/// The way an enemy moves through the enemy area
+#[derive(Component, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum Movement {
+    /// Straight lines from wall to wall
+    Straight,
+    /// Waves `amplitude` pixels to both sides of its path, `frequency` times a second
+    Sine { amplitude: f32, frequency: f32 },
+    /// Flies circles of `radius` along its path, `frequency` rounds a second
+    Circle { radius: f32, frequency: f32 },
+    /// Stands still in the air for `pause` seconds after every `every` seconds of flight
+    Hover { every: f32, pause: f32 },
+    /// Once it reaches a wall, dives `depth` of the way towards the bow with `chance`
+    Swoop { chance: f32, depth: f32 },
+    /// Spawns in groups of `size` that stay together like a flock of birds
+    Flock { size: u32 },
+}
+
+/// Where an enemy would be without the offset of its movement pattern
+#[derive(Component, Deref, DerefMut)]
+pub struct Anchor(pub Vec2);
+
+/// Seconds an enemy has been flying, drives the movement patterns
+#[derive(Component, Deref, DerefMut, Default)]
+pub struct FlightTime(pub f32);
+
+/// An enemy in the middle of a dive towards the bow
+#[derive(Component)]
+pub struct Diving;
+
+/// The flock a `Movement::Flock` enemy belongs to, named after its first member
+#[derive(Component, Clone, Copy, PartialEq, Eq, Hash, Deref)]
+pub struct FlockId(pub Entity);
+
+/// The offset from the anchor of the patterns that wiggle around their path
+fn pattern_offset(movement: &Movement, time: f32, dir: Vec2) -> Vec2 {
+    match *movement {
+        Movement::Sine {
+            amplitude,
+            frequency,
+        } => dir.perp() * amplitude * (TAU * frequency * time).sin(),
+        Movement::Circle { radius, frequency } => {
+            // starts and ends every round on the path itself
+            (Vec2::from_angle(TAU * frequency * time) - Vec2::X) * radius
+        }
+        _ => Vec2::ZERO,
+    }
+}
+
+/// Whether a hovering enemy is taking a break at `time`
+fn is_hovering(movement: &Movement, time: f32) -> bool {
+    match *movement {
+        Movement::Hover { every, pause } => time.rem_euclid(every + pause) > every,
+        _ => false,
+    }
+}
+
+/// How a boid at `pos` wants to change its velocity to stay with its `neighbors`
+/// while heading for `goal`
+fn boid_steering(
+    pos: Vec2,
+    vel: Vec2,
+    neighbors: impl Iterator<Item = (Vec2, Vec2)>,
+    goal: Vec2,
+    speed: f32,
+) -> Vec2 {
+    let mut separation = Vec2::ZERO;
+    let mut alignment = Vec2::ZERO;
+    let mut center = Vec2::ZERO;
+    let mut count = 0;
+    for (other_pos, other_vel) in neighbors {
+        let offset = pos - other_pos;
+        let distance = offset.length();
+        if distance > FLOCK_RADIUS {
+            continue;
+        }
+        if distance > 0. {
+            separation += offset / (distance * distance);
+        }
+        alignment += other_vel;
+        center += other_pos;
+        count += 1;
+    }
+
+    let seek = (goal - pos).normalize_or_zero() * speed - vel;
+    let mut steer = seek * SEEK_WEIGHT;
+    if count > 0 {
+        let count = count as f32;
+        steer += separation * SEPARATION_WEIGHT
+            + (alignment / count - vel) * ALIGNMENT_WEIGHT
+            + (center / count - pos) * COHESION_WEIGHT;
+    }
+    steer
+}
+
+/// A path from `start` on the wall at `side` to another wall, a random one if the path
+/// finder allows. Heads for the middle of that wall if the path finder finds nothing
+fn next_path(
+    rng: &mut GameRng,
+    path_finder: &PathFinder,
+    enemy_area: &EnemyArea,
+    start: Vec2,
+    side: Side,
+    context: &PathContext,
+) -> (Path, Side) {
+    let walls: Vec<&(Side, Vec2, Vec2)> = enemy_area
+        .walls
+        .iter()
+        .filter(|wall| wall.0 != side)
+        .collect();
+    // the previous path ended on `side`, so its heading bounces off there
+    let context = enemy_area
+        .walls
+        .iter()
+        .find(|wall| wall.0 == side)
+        .map_or(*context, |wall| context.off_wall(wall));
+    let first = pick(&mut **rng, 1, &walls)[..][0];
+    // a strategy may not reach every wall from `start`, so the others are tried as well
+    let found = (0..walls.len())
+        .map(|offset| walls[(first + offset) % walls.len()])
+        .find_map(|wall| {
+            let path = path_finder.find_from_start(&mut **rng, start, wall, &context)?;
+            Some((path, wall.0))
+        });
+    found.unwrap_or_else(|| {
+        let end_wall = walls[first];
+        let end = end_wall.1.lerp(end_wall.2, 0.5);
+        (Path { start, end }, end_wall.0)
+    })
+}
+
+#[allow(clippy::type_complexity)]
+pub fn follow_path(
+    time: Res<Time>,
+    mut commands: Commands,
+    mut rng: ResMut<GameRng>,
+    path_finder: Res<PathFinder>,
+    enemy_area: Res<EnemyArea>,
+    bow: Query<&Transform, With<Bow>>,
+    mut query: Query<
+        (
+            Entity,
+            &Speed,
+            &Movement,
+            &mut Anchor,
+            &mut FlightTime,
+            &mut Path,
+            &mut EndsAt,
+            Has<Diving>,
+        ),
+        (With<Enemy>, Without<FlockId>),
+    >,
+) {
+    let bow = bow.get_single().ok();
+    for (entity, speed, movement, mut anchor, mut flight_time, mut path, mut ends_at, diving) in
+        &mut query
+    {
+        **flight_time += time.delta_seconds();
+        if is_hovering(movement, **flight_time) {
+            continue;
+        }
+
+        let speed = if diving {
+            **speed * DIVE_SPEEDUP
+        } else {
+            **speed
+        };
+        let step = speed * time.delta_seconds();
+        let to_end = path.end - **anchor;
+        let px_threshold = 5.;
+        if to_end.length() > step.max(px_threshold) {
+            **anchor += to_end.normalize() * step;
+            continue;
+        }
+
+        **anchor = path.end;
+        if diving {
+            commands.entity(entity).remove::<Diving>();
+        }
+        match (movement, bow) {
+            (Movement::Swoop { chance, depth }, Some(bow))
+                if !diving && rng.gen::<f32>() < *chance =>
+            {
+                *path = Path {
+                    start: **anchor,
+                    end: anchor.lerp(bow.translation.xy(), *depth),
+                };
+                commands.entity(entity).insert(Diving);
+            }
+            _ => {
+                let context = PathContext::new(Some(&path), bow);
+                let (next, side) = next_path(
+                    &mut rng,
+                    &path_finder,
+                    &enemy_area,
+                    **anchor,
+                    **ends_at,
+                    &context,
+                );
+                *path = next;
+                *ends_at = EndsAt(side);
+            }
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub fn flock(
+    time: Res<Time>,
+    mut rng: ResMut<GameRng>,
+    path_finder: Res<PathFinder>,
+    enemy_area: Res<EnemyArea>,
+    mut query: Query<
+        (
+            Entity,
+            &Speed,
+            &FlockId,
+            &mut Anchor,
+            &mut Vel,
+            &mut FlightTime,
+            &mut Path,
+            &mut EndsAt,
+        ),
+        With<Enemy>,
+    >,
+) {
+    let dt = time.delta_seconds();
+    let boids: Vec<(Entity, FlockId, Vec2, Vec2)> = query
+        .iter()
+        .map(|(entity, _, id, anchor, vel, ..)| (entity, *id, **anchor, **vel))
+        .collect();
+
+    let mut new_paths: HashMap<FlockId, (Path, Side)> = HashMap::new();
+    for (entity, speed, id, mut anchor, mut vel, mut flight_time, path, ends_at) in &mut query {
+        **flight_time += dt;
+        let neighbors = boids
+            .iter()
+            .filter(|boid| boid.1 == *id && boid.0 != entity)
+            .map(|boid| (boid.2, boid.3));
+        let steer = boid_steering(**anchor, **vel, neighbors, path.end, **speed);
+        **vel = (**vel + steer * dt).clamp_length_max(**speed);
+        **anchor += **vel * dt;
+
+        if (path.end - **anchor).length() < FLOCK_ARRIVAL && !new_paths.contains_key(id) {
+            let context = PathContext::new(Some(&path), None);
+            let next = next_path(
+                &mut rng,
+                &path_finder,
+                &enemy_area,
+                **anchor,
+                **ends_at,
+                &context,
+            );
+            new_paths.insert(*id, next);
+        }
+    }
+
+    // the whole flock follows whoever arrived first
+    for (.., id, _, _, _, mut path, mut ends_at) in &mut query {
+        if let Some((next, side)) = new_paths.get(id) {
+            *path = *next;
+            *ends_at = EndsAt(*side);
+        }
+    }
+}
+
+pub fn apply_patterns(
+    mut query: Query<(&Movement, &Anchor, &FlightTime, &Path, &mut Transform), With<Enemy>>,
+) {
+    for (movement, anchor, flight_time, path, mut transform) in &mut query {
+        let dir = (path.end - path.start).normalize_or_zero();
+        let offset = pattern_offset(movement, **flight_time, dir);
+        transform.translation = (**anchor + offset).extend(transform.translation.z);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sine_pattern_waves_across_the_path() {
+        let movement = Movement::Sine {
+            amplitude: 10.,
+            frequency: 1.,
+        };
+
+        let offset = pattern_offset(&movement, 0.25, Vec2::X);
+        assert!((offset - Vec2::new(0., 10.)).length() < 1e-4);
+        let offset = pattern_offset(&movement, 0.5, Vec2::X);
+        assert!(offset.length() < 1e-4);
+    }
+
+    #[test]
+    fn hovering_enemy_pauses_between_flights() {
+        let movement = Movement::Hover {
+            every: 2.,
+            pause: 1.,
+        };
+
+        assert!(!is_hovering(&movement, 1.));
+        assert!(is_hovering(&movement, 2.5));
+        assert!(!is_hovering(&movement, 3.5));
+    }
+
+    #[test]
+    fn boids_keep_their_distance() {
+        let steer = boid_steering(
+            Vec2::ZERO,
+            Vec2::ZERO,
+            [(Vec2::new(5., 0.), Vec2::ZERO)].into_iter(),
+            Vec2::ZERO,
+            100.,
+        );
+
+        assert!(steer.x < 0.);
+    }
+}
@@ -0,0 +1,495 @@
+use bevy::math::{Vec2, Vec3, Vec3Swizzles};
+use bevy::prelude::{Reflect, Resource, Transform};
+use rand::{Rng, RngCore};
+
+use super::{Path, Side};
+
+/// How often a strategy draws a random path before it gives up
+const MAX_TRIES: usize = 32;
+/// How far the line of fire of the bow reaches
+const LINE_OF_FIRE_LENGTH: f32 = 10000.;
+/// How many headings to each side `MaxAngleChange` tries when no random path fits
+const ANGLE_STEPS: usize = 8;
+
+/// A wall of an `Area`
+pub type Wall = (Side, Vec2, Vec2);
+
+/// What a strategy might want to know about the world when picking a path
+#[derive(Default, Clone, Copy)]
+pub struct PathContext {
+    /// The direction of the path the enemy flew along before
+    pub previous: Option<Vec2>,
+    /// Position and aiming direction of the bow
+    pub bow: Option<(Vec2, Vec2)>,
+}
+
+impl PathContext {
+    pub fn new(previous: Option<&Path>, bow: Option<&Transform>) -> Self {
+        Self {
+            previous: previous.map(|path| path.end - path.start),
+            // the bow sprite points the way its arrows fly, see `rotate_bow`
+            bow: bow.map(|tr| (tr.translation.xy(), (tr.rotation * Vec3::X).xy())),
+        }
+    }
+
+    /// The context for a path that starts on `wall`, with the previous heading bounced off it
+    pub fn off_wall(mut self, wall: &Wall) -> Self {
+        let along = (wall.2 - wall.1).normalize_or_zero();
+        self.previous = self
+            .previous
+            .map(|heading| 2. * heading.dot(along) * along - heading);
+        self
+    }
+}
+
+/// Picks paths between walls of the enemy area.
+///
+/// Paths are drawn at random and the first one the strategy `accepts` is taken,
+/// or the one with the best `score` if the strategy has one.
+/// After `MAX_TRIES` draws without an accepted path the strategy gives up.
+pub trait PathFindingStrategy {
+    /// Whether `path` is good enough to fly along
+    fn accepts(&self, path: &Path, context: &PathContext) -> bool;
+
+    /// Strategies with a score keep drawing and return the best accepted path
+    fn score(&self, _path: &Path) -> Option<f32> {
+        None
+    }
+
+    /// A path from somewhere on `from` to somewhere on `to`
+    fn find(
+        &self,
+        rng: &mut dyn RngCore,
+        from: &Wall,
+        to: &Wall,
+        context: &PathContext,
+    ) -> Option<Path> {
+        self.search(rng, context, &mut |rng| Path {
+            start: random_point_on_line(rng, from.1, from.2),
+            end: random_point_on_line(rng, to.1, to.2),
+        })
+    }
+
+    /// A path from `start` to somewhere on `to`
+    fn find_from_start(
+        &self,
+        rng: &mut dyn RngCore,
+        start: Vec2,
+        to: &Wall,
+        context: &PathContext,
+    ) -> Option<Path> {
+        self.search(rng, context, &mut |rng| Path {
+            start,
+            end: random_point_on_line(rng, to.1, to.2),
+        })
+    }
+
+    /// Draws up to `MAX_TRIES` paths and returns the accepted one
+    fn search(
+        &self,
+        rng: &mut dyn RngCore,
+        context: &PathContext,
+        draw: &mut dyn FnMut(&mut dyn RngCore) -> Path,
+    ) -> Option<Path> {
+        let mut best: Option<(Path, f32)> = None;
+        for _ in 0..MAX_TRIES {
+            let path = draw(rng);
+            if !self.accepts(&path, context) {
+                continue;
+            }
+            let Some(score) = self.score(&path) else {
+                return Some(path);
+            };
+            if !best.is_some_and(|(_, best)| score <= best) {
+                best = Some((path, score));
+            }
+        }
+        best.map(|(path, _)| path)
+    }
+}
+
+/// Paths that are at least this long
+#[derive(Clone, Copy, Reflect)]
+pub struct MinLength(pub f32);
+
+impl PathFindingStrategy for MinLength {
+    fn accepts(&self, path: &Path, _context: &PathContext) -> bool {
+        path.length() >= self.0
+    }
+}
+
+/// Paths that turn by at most `max_angle` radians from the previous path
+#[derive(Clone, Copy, Reflect)]
+pub struct MaxAngleChange {
+    pub min: f32,
+    pub max_angle: f32,
+}
+
+impl PathFindingStrategy for MaxAngleChange {
+    fn accepts(&self, path: &Path, context: &PathContext) -> bool {
+        let turn = context
+            .previous
+            .map_or(0., |previous| previous.angle_between(path.end - path.start));
+        path.length() >= self.min && turn.abs() <= self.max_angle
+    }
+
+    /// Random ends on `to` rarely fit a narrow turn, so without one the headings
+    /// closest to the previous one are followed until they reach `to`
+    fn find_from_start(
+        &self,
+        rng: &mut dyn RngCore,
+        start: Vec2,
+        to: &Wall,
+        context: &PathContext,
+    ) -> Option<Path> {
+        let random = self.search(rng, context, &mut |rng| Path {
+            start,
+            end: random_point_on_line(rng, to.1, to.2),
+        });
+        random.or_else(|| {
+            let previous = context.previous?.normalize_or_zero();
+            (0..ANGLE_STEPS)
+                .map(|step| self.max_angle * step as f32 / ANGLE_STEPS as f32)
+                .flat_map(|angle| [angle, -angle])
+                .filter_map(|angle| ray_hit(start, Vec2::from_angle(angle).rotate(previous), to))
+                .map(|end| Path { start, end })
+                .find(|path| self.accepts(path, context))
+        })
+    }
+}
+
+/// Paths that stay at least `clearance` away from where the bow is aiming
+#[derive(Clone, Copy, Reflect)]
+pub struct AvoidLineOfFire {
+    pub min: f32,
+    pub clearance: f32,
+}
+
+impl PathFindingStrategy for AvoidLineOfFire {
+    fn accepts(&self, path: &Path, context: &PathContext) -> bool {
+        let in_line_of_fire = context.bow.is_some_and(|(pos, aim)| {
+            let end = pos + aim.normalize_or_zero() * LINE_OF_FIRE_LENGTH;
+            segment_distance(path.start, path.end, pos, end) < self.clearance
+        });
+        path.length() >= self.min && !in_line_of_fire
+    }
+}
+
+/// Prefers long paths that cross the area diagonally
+#[derive(Clone, Copy, Reflect)]
+pub struct LongDiagonals {
+    pub min: f32,
+}
+
+impl PathFindingStrategy for LongDiagonals {
+    fn accepts(&self, path: &Path, _context: &PathContext) -> bool {
+        path.length() >= self.min
+    }
+
+    fn score(&self, path: &Path) -> Option<f32> {
+        let dir = path.end - path.start;
+        // 1 for lines at 45°, 0 for horizontal and vertical ones
+        let diagonal = (2. * dir.y.atan2(dir.x)).sin().abs();
+        Some(path.length() * diagonal)
+    }
+}
+
+/// The strategy enemies use to pick their paths
+#[derive(Resource, Clone, Copy, Reflect)]
+pub enum PathFinder {
+    MinLength(MinLength),
+    MaxAngleChange(MaxAngleChange),
+    AvoidLineOfFire(AvoidLineOfFire),
+    LongDiagonals(LongDiagonals),
+}
+
+impl PathFinder {
+    fn strategy(&self) -> &dyn PathFindingStrategy {
+        match self {
+            PathFinder::MinLength(strategy) => strategy,
+            PathFinder::MaxAngleChange(strategy) => strategy,
+            PathFinder::AvoidLineOfFire(strategy) => strategy,
+            PathFinder::LongDiagonals(strategy) => strategy,
+        }
+    }
+
+    /// Changes the minimal path length of the strategy, e.g. after the area changed
+    pub fn set_min(&mut self, min: f32) {
+        match self {
+            PathFinder::MinLength(strategy) => strategy.0 = min,
+            PathFinder::MaxAngleChange(strategy) => strategy.min = min,
+            PathFinder::AvoidLineOfFire(strategy) => strategy.min = min,
+            PathFinder::LongDiagonals(strategy) => strategy.min = min,
+        }
+    }
+}
+
+impl PathFindingStrategy for PathFinder {
+    fn accepts(&self, path: &Path, context: &PathContext) -> bool {
+        self.strategy().accepts(path, context)
+    }
+
+    fn score(&self, path: &Path) -> Option<f32> {
+        self.strategy().score(path)
+    }
+
+    fn find(
+        &self,
+        rng: &mut dyn RngCore,
+        from: &Wall,
+        to: &Wall,
+        context: &PathContext,
+    ) -> Option<Path> {
+        self.strategy().find(rng, from, to, context)
+    }
+
+    fn find_from_start(
+        &self,
+        rng: &mut dyn RngCore,
+        start: Vec2,
+        to: &Wall,
+        context: &PathContext,
+    ) -> Option<Path> {
+        self.strategy().find_from_start(rng, start, to, context)
+    }
+}
+
+fn random_point_on_line(rng: &mut dyn RngCore, from: Vec2, to: Vec2) -> Vec2 {
+    let t = rng.gen::<f32>();
+    from.lerp(to, t)
+}
+
+/// Where the ray from `origin` in `dir` crosses `wall`, if it does
+fn ray_hit(origin: Vec2, dir: Vec2, wall: &Wall) -> Option<Vec2> {
+    let along = wall.2 - wall.1;
+    let denominator = dir.perp_dot(along);
+    if denominator.abs() < f32::EPSILON {
+        return None;
+    }
+    let t = (wall.1 - origin).perp_dot(along) / denominator;
+    let s = (wall.1 - origin).perp_dot(dir) / denominator;
+    (t > 0. && (0. ..=1.).contains(&s)).then(|| origin + dir * t)
+}
+
+/// The shortest distance between the line segments `a0`-`a1` and `b0`-`b1`
+fn segment_distance(a0: Vec2, a1: Vec2, b0: Vec2, b1: Vec2) -> f32 {
+    let cross = |o: Vec2, p: Vec2, q: Vec2| (p - o).perp_dot(q - o);
+    let d1 = cross(b0, b1, a0);
+    let d2 = cross(b0, b1, a1);
+    let d3 = cross(a0, a1, b0);
+    let d4 = cross(a0, a1, b1);
+    if d1 * d2 < 0. && d3 * d4 < 0. {
+        return 0.;
+    }
+
+    let point_distance = |p: Vec2, s0: Vec2, s1: Vec2| {
+        let segment = s1 - s0;
+        let t = if segment.length_squared() > 0. {
+            ((p - s0).dot(segment) / segment.length_squared()).clamp(0., 1.)
+        } else {
+            0.
+        };
+        (p - (s0 + segment * t)).length()
+    };
+    point_distance(a0, b0, b1)
+        .min(point_distance(a1, b0, b1))
+        .min(point_distance(b0, a0, a1))
+        .min(point_distance(b1, a0, a1))
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::rng::{GameRng, Seed};
+    use crate::{pick, Area};
+
+    fn walls() -> [(Side, Vec2, Vec2); 4] {
+        Area::new(Vec2::new(0., 100.), Vec2::new(200., -100.)).walls
+    }
+
+    // the order `Area::new` puts the walls in
+    const TOP: usize = 0;
+    const RIGHT: usize = 1;
+    const BOTTOM: usize = 2;
+    const LEFT: usize = 3;
+
+    fn wall(index: usize) -> (Side, Vec2, Vec2) {
+        walls()[index]
+    }
+
+    #[test]
+    fn path_finder_gives_up_on_impossible_paths() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let finder = MinLength(1000.);
+        let path = finder.find(&mut rng, &wall(TOP), &wall(BOTTOM), &PathContext::default());
+        assert!(path.is_none());
+    }
+
+    #[test]
+    fn min_length_paths_are_long_enough() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let finder = MinLength(150.);
+        for _ in 0..20 {
+            let path = finder
+                .find(&mut rng, &wall(LEFT), &wall(TOP), &PathContext::default())
+                .unwrap();
+            assert!(path.length() >= 150.);
+        }
+    }
+
+    #[test]
+    fn max_angle_change_keeps_heading() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let finder = MaxAngleChange {
+            min: 0.,
+            max_angle: 0.5,
+        };
+        let context = PathContext {
+            previous: Some(Vec2::X),
+            bow: None,
+        };
+        let start = Vec2::new(0., 0.);
+        for _ in 0..20 {
+            let path = finder
+                .find_from_start(&mut rng, start, &wall(RIGHT), &context)
+                .unwrap();
+            assert!(Vec2::X.angle_between(path.end - path.start).abs() <= 0.5);
+        }
+        // turning back is never allowed
+        let back = finder.find_from_start(&mut rng, start, &wall(LEFT), &context);
+        assert!(back.is_none());
+    }
+
+    #[test]
+    fn paths_avoid_the_line_of_fire() {
+        let mut rng = StdRng::seed_from_u64(4);
+        let finder = AvoidLineOfFire {
+            min: 0.,
+            clearance: 20.,
+        };
+        // the bow aims horizontally through the middle of the area
+        let context = PathContext {
+            previous: None,
+            bow: Some((Vec2::new(-100., 0.), Vec2::X)),
+        };
+        for _ in 0..20 {
+            let path = finder
+                .find(&mut rng, &wall(LEFT), &wall(TOP), &context)
+                .unwrap();
+            assert!(path.start.y >= 20. && path.end.y >= 20.);
+        }
+        // every path from top to bottom crosses the line of fire
+        let crossing = finder.find(&mut rng, &wall(TOP), &wall(BOTTOM), &context);
+        assert!(crossing.is_none());
+    }
+
+    #[test]
+    fn long_diagonals_beat_straight_paths() {
+        let mut rng = StdRng::seed_from_u64(5);
+        let finder = LongDiagonals { min: 0. };
+        let straight = Path {
+            start: Vec2::new(0., 0.),
+            end: Vec2::new(200., 0.),
+        };
+        let diagonal = Path {
+            start: Vec2::new(0., 100.),
+            end: Vec2::new(200., -100.),
+        };
+        assert!(finder.score(&diagonal) > finder.score(&straight));
+
+        let path = finder
+            .find(&mut rng, &wall(LEFT), &wall(RIGHT), &PathContext::default())
+            .unwrap();
+        let dir = (path.end - path.start).normalize();
+        assert!(dir.y.abs() > 0.3);
+    }
+
+    #[test]
+    fn segment_distance_is_zero_for_crossing_segments() {
+        let distance = segment_distance(
+            Vec2::new(-1., 0.),
+            Vec2::new(1., 0.),
+            Vec2::new(0., -1.),
+            Vec2::new(0., 1.),
+        );
+        assert_eq!(distance, 0.);
+        let distance = segment_distance(
+            Vec2::new(-1., 2.),
+            Vec2::new(1., 2.),
+            Vec2::new(0., -1.),
+            Vec2::new(0., 1.),
+        );
+        assert_eq!(distance, 1.);
+    }
+
+    #[test]
+    fn same_seed_picks_the_same_paths() {
+        let paths = |seed| {
+            let mut rng = GameRng::new(Seed(seed));
+            let finder = PathFinder::MinLength(MinLength(100.));
+            (0..10)
+                .map(|_| {
+                    let walls = walls();
+                    let is = pick(&mut *rng, 2, &walls.to_vec());
+                    let path = finder
+                        .find(
+                            &mut *rng,
+                            &walls[is[0]],
+                            &walls[is[1]],
+                            &PathContext::default(),
+                        )
+                        .unwrap();
+                    (path.start, path.end)
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(paths(9), paths(9));
+        assert_ne!(paths(9), paths(10));
+    }
+
+    #[test]
+    fn max_angle_change_keeps_flying_from_wall_to_wall() {
+        // the enemy area of an 800 by 600 window
+        let area = Area::new(Vec2::new(0., 300.), Vec2::new(400., -300.)).shrink(0.1);
+        let finder = MaxAngleChange {
+            min: 0.,
+            max_angle: 0.5,
+        };
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut on = area.walls[LEFT];
+        let start = Vec2::new(on.1.x, 0.);
+        let mut path = Path {
+            start: start + Vec2::new(1., 0.2),
+            end: start,
+        };
+        for _ in 0..50 {
+            let context = PathContext::new(Some(&path), None).off_wall(&on);
+            let heading = context.previous.unwrap();
+            let (next, wall) = area
+                .walls
+                .iter()
+                .filter(|wall| wall.0 != on.0)
+                .find_map(|wall| {
+                    let next = finder.find_from_start(&mut rng, path.end, wall, &context)?;
+                    Some((next, *wall))
+                })
+                .expect("every heading leads to some wall");
+            assert!(heading.angle_between(next.end - next.start).abs() <= 0.5);
+            path = next;
+            on = wall;
+        }
+    }
+
+    #[test]
+    fn headings_bounce_off_walls() {
+        let context = PathContext {
+            previous: Some(Vec2::new(1., 1.)),
+            bow: None,
+        };
+        let bounced = context.off_wall(&wall(RIGHT));
+        assert_eq!(bounced.previous, Some(Vec2::new(-1., 1.)));
+    }
+}
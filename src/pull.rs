@@ -0,0 +1,136 @@
+use bevy::asset::Assets;
+use bevy::input::{keyboard::KeyCode, ButtonInput};
+use bevy::math::{Vec2, Vec3Swizzles};
+use bevy::prelude::{Component, Gizmos, Query, Reflect, Res, ResMut, Resource, Transform, With};
+use bevy::render::color::Color;
+use bevy::sprite::{TextureAtlas, TextureAtlasLayout};
+use bevy::text::Text;
+use bevy::time::Time;
+use serde::{Deserialize, Serialize};
+
+use crate::atlas::frame_size;
+
+use super::{Bow, BowPullTime, Fixed, Mouse, BOW_FULL_PULL_TIME};
+
+/// Key in the menu that switches between the pull modes
+const PULL_KEY: KeyCode = KeyCode::KeyV;
+/// The shortest drag for a full draw, whatever `PullMode::Drag` says
+const MIN_DRAG_DISTANCE: f32 = 1.;
+
+/// What decides how hard the bow is drawn
+#[derive(Resource, Reflect, Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+pub enum PullMode {
+    /// The longer the bow is held drawn, up to `BOW_FULL_PULL_TIME`
+    #[default]
+    Time,
+    /// The further the cursor is dragged from where the draw started, like a slingshot
+    Drag {
+        /// Pixels to drag for a full draw, at least `MIN_DRAG_DISTANCE`
+        max_distance: f32,
+    },
+}
+
+impl PullMode {
+    pub const DRAG: PullMode = PullMode::Drag { max_distance: 250. };
+
+    fn describe(&self) -> &'static str {
+        match self {
+            PullMode::Time => "hold to pull",
+            PullMode::Drag { .. } => "drag to pull",
+        }
+    }
+}
+
+/// Where the cursor was when the bow was drawn, the drag is measured from here
+#[derive(Component, Default)]
+pub struct DragAnchor(Option<Vec2>);
+
+/// How far `mouse` is dragged from `anchor`, 0 for nothing and 1 for a full draw
+fn drag_power(anchor: Vec2, mouse: Vec2, max_distance: f32) -> f32 {
+    (anchor.distance(mouse) / max_distance.max(MIN_DRAG_DISTANCE)).min(1.)
+}
+
+/// Sets `BowPullTime` from the time held or the distance dragged, depending on the `PullMode`
+pub fn pull_bow(
+    time: Res<Time>,
+    mode: Res<PullMode>,
+    mouse: Res<Mouse>,
+    mut bow: Query<(&Fixed, &mut BowPullTime, &mut DragAnchor), With<Bow>>,
+) {
+    for (fixed, mut pull_time, mut anchor) in &mut bow {
+        if !**fixed {
+            **pull_time = 0.;
+            anchor.0 = None;
+            continue;
+        }
+        match *mode {
+            PullMode::Time => {
+                **pull_time = (**pull_time + time.delta_seconds()).min(BOW_FULL_PULL_TIME);
+            }
+            PullMode::Drag { max_distance } => {
+                let anchor = *anchor.0.get_or_insert(**mouse);
+                **pull_time = drag_power(anchor, **mouse, max_distance) * BOW_FULL_PULL_TIME;
+            }
+        }
+    }
+}
+
+/// The string of a bow drawn by dragging, stretched back and redder the further it is pulled
+pub fn draw_string(
+    mode: Res<PullMode>,
+    mouse: Res<Mouse>,
+    layouts: Res<Assets<TextureAtlasLayout>>,
+    bow: Query<(&Transform, &Fixed, &BowPullTime, &DragAnchor, &TextureAtlas), With<Bow>>,
+    mut gizmos: Gizmos,
+) {
+    let PullMode::Drag { max_distance } = *mode else {
+        return;
+    };
+    let max_distance = max_distance.max(MIN_DRAG_DISTANCE);
+    for (tr, fixed, pull_time, anchor, atlas) in &bow {
+        let Some(anchor) = anchor.0.filter(|_| **fixed) else {
+            continue;
+        };
+        let power = **pull_time / BOW_FULL_PULL_TIME;
+        let half = frame_size(atlas, &layouts).y / 2.;
+        // a full drag pulls the string back by half the bow
+        let pull = (**mouse - anchor).clamp_length_max(max_distance) / max_distance;
+        let nock = tr.translation.xy() + pull * half;
+        let tip = (tr.rotation * Vec2::Y.extend(0.)).xy() * half;
+        let color = Color::rgb(1., 1. - power, 1. - power);
+        gizmos.line_2d(tr.translation.xy() + tip, nock, color);
+        gizmos.line_2d(tr.translation.xy() - tip, nock, color);
+        gizmos.circle_2d(anchor, max_distance, color.with_a(0.2));
+    }
+}
+
+#[derive(Component)]
+pub struct PullModeUi;
+
+pub fn toggle_pull_mode(keys: Res<ButtonInput<KeyCode>>, mut mode: ResMut<PullMode>) {
+    if keys.just_pressed(PULL_KEY) {
+        *mode = match *mode {
+            PullMode::Time => PullMode::DRAG,
+            PullMode::Drag { .. } => PullMode::Time,
+        };
+    }
+}
+
+pub fn update_pull_mode_ui(mode: Res<PullMode>, mut query: Query<&mut Text, With<PullModeUi>>) {
+    for mut text in &mut query {
+        text.sections[0].value = format!("Pull: {} (press {PULL_KEY:?})", mode.describe());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drag_power_stays_between_zero_and_one() {
+        assert_eq!(drag_power(Vec2::ZERO, Vec2::Y * 1000., 250.), 1.);
+        // a drag distance of 0 from the inspector or a recording doesn't divide by 0
+        assert_eq!(drag_power(Vec2::ZERO, Vec2::ZERO, 0.), 0.);
+        assert_eq!(drag_power(Vec2::ZERO, Vec2::Y, 0.), 1.);
+    }
+}
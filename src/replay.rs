@@ -0,0 +1,195 @@
+use std::path::{Path, PathBuf};
+
+use bevy::app::AppExit;
+use bevy::log::{error, info};
+use bevy::math::Vec2;
+use bevy::prelude::{DetectChanges, DetectChangesMut, EventReader, Res, ResMut, Resource};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::controls::ControlScheme;
+use crate::fatigue::Fatigue;
+use crate::mode::GameMode;
+use crate::pull::PullMode;
+use crate::rng::Seed;
+
+use super::{DrawButton, Mouse, MoveInput, Playfield, SelectedArrow};
+
+/// The player input of one fixed tick
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+pub struct InputFrame {
+    /// The mouse position in world coordinates
+    pub mouse: (f32, f32),
+    /// Where keys or a stick pushed the bow
+    #[serde(default)]
+    pub movement: (f32, f32),
+    /// Whether the bow is drawn
+    pub pressed: bool,
+    /// Whether the draw was cancelled instead of shot
+    #[serde(default)]
+    pub cancelled: bool,
+    /// The index of the selected arrow
+    pub arrow: usize,
+    /// The new size of the playfield, if the window was resized
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub playfield: Option<(f32, f32)>,
+}
+
+/// Everything needed to play a game again: the seed, the mode and the input of every fixed tick
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, Default)]
+pub struct Recording {
+    pub seed: u64,
+    #[serde(default)]
+    pub mode: GameMode,
+    #[serde(default)]
+    pub fatigue: Fatigue,
+    #[serde(default)]
+    pub controls: ControlScheme,
+    #[serde(default)]
+    pub pull_mode: PullMode,
+    /// The size of the playfield when the game started, older recordings play in the window
+    #[serde(default)]
+    pub playfield: Option<(f32, f32)>,
+    pub frames: Vec<InputFrame>,
+}
+
+#[derive(Debug, Error)]
+pub enum RecordingError {
+    #[error("could not read or write the recording: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("could not parse the recording: {0}")]
+    Parse(#[from] ron::error::SpannedError),
+    #[error("could not serialize the recording: {0}")]
+    Serialize(#[from] ron::Error),
+}
+
+impl Recording {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, RecordingError> {
+        let bytes = std::fs::read(path)?;
+        Ok(ron::de::from_bytes(&bytes)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), RecordingError> {
+        let text = ron::ser::to_string(self)?;
+        std::fs::write(path, text)?;
+        Ok(())
+    }
+}
+
+/// Records the input of every fixed tick, written to `path` when the app exits
+#[derive(Resource)]
+pub struct Recorder {
+    pub path: PathBuf,
+    pub recording: Recording,
+}
+
+impl Recorder {
+    pub fn new(path: impl Into<PathBuf>, seed: Seed) -> Self {
+        Recorder {
+            path: path.into(),
+            recording: Recording {
+                seed: *seed,
+                ..Recording::default()
+            },
+        }
+    }
+}
+
+/// Feeds a recording back into the game instead of the live input
+#[derive(Resource)]
+pub struct Replay {
+    recording: Recording,
+    tick: usize,
+}
+
+impl Replay {
+    pub fn new(recording: Recording) -> Self {
+        Replay { recording, tick: 0 }
+    }
+
+    pub fn seed(&self) -> Seed {
+        Seed(self.recording.seed)
+    }
+
+    pub fn mode(&self) -> GameMode {
+        self.recording.mode
+    }
+
+    pub fn fatigue(&self) -> Fatigue {
+        self.recording.fatigue
+    }
+
+    pub fn controls(&self) -> ControlScheme {
+        self.recording.controls
+    }
+
+    pub fn pull_mode(&self) -> PullMode {
+        self.recording.pull_mode
+    }
+
+    pub fn playfield(&self) -> Option<Vec2> {
+        self.recording
+            .playfield
+            .map(|(width, height)| Vec2::new(width, height))
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.tick >= self.recording.frames.len()
+    }
+}
+
+pub fn record_input(
+    mouse: Res<Mouse>,
+    movement: Res<MoveInput>,
+    button: Res<DrawButton>,
+    selected: Res<SelectedArrow>,
+    playfield: Res<Playfield>,
+    mut recorder: ResMut<Recorder>,
+) {
+    recorder.recording.frames.push(InputFrame {
+        mouse: (mouse.x, mouse.y),
+        movement: (movement.x, movement.y),
+        pressed: button.pressed(),
+        cancelled: button.cancelled(),
+        arrow: **selected,
+        playfield: playfield.is_changed().then_some((playfield.x, playfield.y)),
+    });
+}
+
+pub fn save_recording(mut exits: EventReader<AppExit>, recorder: Res<Recorder>) {
+    if exits.read().next().is_none() {
+        return;
+    }
+    match recorder.recording.save(&recorder.path) {
+        Ok(()) => info!("saved recording to {}", recorder.path.display()),
+        Err(err) => error!("{err}"),
+    }
+}
+
+pub fn replay_input(
+    mut replay: ResMut<Replay>,
+    mut mouse: ResMut<Mouse>,
+    mut movement: ResMut<MoveInput>,
+    mut button: ResMut<DrawButton>,
+    mut selected: ResMut<SelectedArrow>,
+    mut playfield: ResMut<Playfield>,
+) {
+    let Some(frame) = replay.recording.frames.get(replay.tick).copied() else {
+        // the bow stays where it is once the recording ran out
+        button.set(false, false);
+        **movement = Vec2::ZERO;
+        return;
+    };
+    replay.tick += 1;
+    if replay.is_finished() {
+        info!("replay finished");
+    }
+
+    **mouse = Vec2::new(frame.mouse.0, frame.mouse.1);
+    **movement = Vec2::new(frame.movement.0, frame.movement.1);
+    button.set(frame.pressed, frame.cancelled);
+    **selected = frame.arrow;
+    if let Some((width, height)) = frame.playfield {
+        playfield.set_if_neq(Playfield(Vec2::new(width, height)));
+    }
+}
@@ -0,0 +1,46 @@
+use bevy::log::info;
+use bevy::prelude::{Deref, DerefMut, Res, Resource};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use crate::cli::arg_value;
+
+/// Environment variable the seed is read from if it isn't given on the command line
+const SEED_VAR: &str = "BEVY_BOW_SEED";
+
+/// The seed the game was started with
+#[derive(Resource, Clone, Copy, Deref, PartialEq, Eq, Debug)]
+pub struct Seed(pub u64);
+
+impl Seed {
+    /// The seed from `--seed <n>` or `BEVY_BOW_SEED`, a random one otherwise
+    pub fn from_args(args: &[String]) -> Self {
+        arg_value(args, "--seed")
+            .or_else(|| std::env::var(SEED_VAR).ok())
+            .and_then(|seed| match seed.parse() {
+                Ok(seed) => Some(Seed(seed)),
+                Err(err) => {
+                    // runs before the log plugin is set up
+                    eprintln!("ignoring seed {seed:?}: {err}");
+                    None
+                }
+            })
+            .unwrap_or_else(|| Seed(rand::random()))
+    }
+}
+
+/// Every gameplay system draws its random numbers from here,
+/// so the same seed and the same inputs play out the same game
+#[derive(Resource, Deref, DerefMut)]
+pub struct GameRng(StdRng);
+
+impl GameRng {
+    pub fn new(seed: Seed) -> Self {
+        GameRng(StdRng::seed_from_u64(*seed))
+    }
+}
+
+/// Logs the seed so a run can be reproduced with `--seed`
+pub fn log_seed(seed: Res<Seed>) {
+    info!("seed {}", **seed);
+}
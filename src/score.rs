@@ -0,0 +1,216 @@
+use bevy::math::{Vec2, Vec3};
+use bevy::prelude::{
+    default, Commands, Component, Entity, EventReader, EventWriter, Query, Reflect, Res, ResMut,
+    Resource, Transform,
+};
+use bevy::render::color::Color;
+use bevy::text::{Text, Text2dBundle, TextStyle};
+use bevy::time::{Time, Timer, TimerMode};
+use bevy::utils::HashMap;
+
+use crate::state::InGame;
+
+use super::{DespawnEvent, EnemyKilledEvent, Scoreboard};
+
+/// Enemies of this size get their plain score value, smaller ones more and bigger ones less
+const REFERENCE_SIZE: f32 = 80.;
+/// Shots that flew further than this get the long shot bonus
+const LONG_SHOT_DISTANCE: f32 = 600.;
+const LONG_SHOT_BONUS: f32 = 1.5;
+/// How far ahead of its center an enemy has to be hit for a headshot, relative to its size
+const HEAD_ZONE: f32 = 0.25;
+const HEADSHOT_BONUS: f32 = 2.;
+/// Every kill after the first one with the same arrow adds this much
+const MULTI_KILL_BONUS: f32 = 0.5;
+/// Seconds until the combo breaks without another kill
+const COMBO_TIME: f32 = 3.;
+/// How much every kill in a combo adds to the multiplier
+const COMBO_STEP: f32 = 0.1;
+const MAX_MULTIPLIER: f32 = 4.;
+const POPUP_TIME: f32 = 0.8;
+const POPUP_SPEED: f32 = 60.;
+
+/// Kills in a row without `COMBO_TIME` passing between them
+#[derive(Resource, Reflect)]
+pub struct Combo {
+    pub count: u32,
+    pub best: u32,
+    timer: Timer,
+    /// Kills per arrow in this combo, for the multi-kill bonus
+    #[reflect(ignore)]
+    kills_by_arrow: HashMap<Entity, u32>,
+}
+
+impl Default for Combo {
+    fn default() -> Self {
+        Combo {
+            count: 0,
+            best: 0,
+            timer: Timer::from_seconds(COMBO_TIME, TimerMode::Once),
+            kills_by_arrow: HashMap::new(),
+        }
+    }
+}
+
+impl Combo {
+    /// What the next kill gets multiplied with
+    pub fn multiplier(&self) -> f32 {
+        (1. + self.count as f32 * COMBO_STEP).min(MAX_MULTIPLIER)
+    }
+
+    /// Counts a kill by `arrow`, returns how many kills the arrow has now
+    pub fn add(&mut self, arrow: Entity) -> u32 {
+        self.count += 1;
+        self.best = self.best.max(self.count);
+        self.timer.reset();
+        let kills = self.kills_by_arrow.entry(arrow).or_default();
+        *kills += 1;
+        *kills
+    }
+
+    /// Breaks the combo once `COMBO_TIME` passed since the last kill
+    pub fn tick(&mut self, time: &Time) {
+        if self.count == 0 {
+            return;
+        }
+        self.timer.tick(time.delta());
+        if self.timer.finished() {
+            self.count = 0;
+            self.kills_by_arrow.clear();
+        }
+    }
+}
+
+/// Whether a hit at `hit` lands ahead of the center of an enemy at `center` flying towards `dir`
+pub fn is_headshot(hit: Vec2, center: Vec2, dir: Vec2, size: f32) -> bool {
+    (hit - center).dot(dir.normalize_or_zero()) > size * HEAD_ZONE
+}
+
+/// Points for a kill before the combo multiplier
+fn kill_points(kill: &EnemyKilledEvent, arrow_kills: u32) -> f32 {
+    let size = (REFERENCE_SIZE / kill.size).clamp(0.5, 2.);
+    let long_shot = if kill.shot_distance > LONG_SHOT_DISTANCE {
+        LONG_SHOT_BONUS
+    } else {
+        1.
+    };
+    let headshot = if kill.headshot { HEADSHOT_BONUS } else { 1. };
+    let multi_kill = 1. + arrow_kills.saturating_sub(1) as f32 * MULTI_KILL_BONUS;
+    kill.value as f32 * size * long_shot * headshot * multi_kill
+}
+
+/// A number floating up from where points were scored
+#[derive(Component)]
+pub struct ScorePopup(Timer);
+
+pub fn score_kills(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut kills: EventReader<EnemyKilledEvent>,
+    mut score: ResMut<Scoreboard>,
+    mut combo: ResMut<Combo>,
+) {
+    combo.tick(&time);
+    for kill in kills.read() {
+        let multiplier = combo.multiplier();
+        let arrow_kills = combo.add(kill.arrow);
+        // every kill is worth something
+        let points = (kill_points(kill, arrow_kills) * multiplier)
+            .round()
+            .max(1.) as u32;
+        **score += points;
+
+        let mut label = format!("+{points}");
+        if kill.headshot {
+            label.push_str(" head");
+        }
+        if arrow_kills > 1 {
+            label.push_str(&format!(" x{arrow_kills}"));
+        }
+        commands.spawn((
+            Text2dBundle {
+                text: Text::from_section(
+                    label,
+                    TextStyle {
+                        font_size: 24.,
+                        color: Color::GOLD,
+                        ..default()
+                    },
+                ),
+                transform: Transform::from_translation(kill.pos.extend(1.)),
+                ..default()
+            },
+            ScorePopup(Timer::from_seconds(POPUP_TIME, TimerMode::Once)),
+            InGame,
+        ));
+    }
+}
+
+pub fn float_popups(
+    time: Res<Time>,
+    mut popups: Query<(Entity, &mut Transform, &mut Text, &mut ScorePopup)>,
+    mut despawns: EventWriter<DespawnEvent>,
+) {
+    for (entity, mut tr, mut text, mut popup) in &mut popups {
+        popup.0.tick(time.delta());
+        tr.translation += Vec3::Y * POPUP_SPEED * time.delta_seconds();
+        for section in &mut text.sections {
+            section.style.color.set_a(popup.0.fraction_remaining());
+        }
+        if popup.0.finished() {
+            despawns.send(DespawnEvent(entity));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::utils::Duration;
+
+    use super::*;
+
+    fn kill(size: f32, shot_distance: f32, headshot: bool) -> EnemyKilledEvent {
+        EnemyKilledEvent {
+            pos: Vec2::ZERO,
+            arrow: Entity::from_raw(0),
+            value: 10,
+            size,
+            shot_distance,
+            headshot,
+        }
+    }
+
+    #[test]
+    fn small_far_and_headshot_kills_are_worth_more() {
+        let plain = kill_points(&kill(80., 0., false), 1);
+        assert_eq!(plain, 10.);
+        assert_eq!(kill_points(&kill(40., 0., false), 1), 20.);
+        assert_eq!(kill_points(&kill(160., 0., false), 1), 5.);
+        assert_eq!(kill_points(&kill(80., 1000., false), 1), 15.);
+        assert_eq!(kill_points(&kill(80., 0., true), 1), 20.);
+        // the third kill of one arrow
+        assert_eq!(kill_points(&kill(80., 0., false), 3), 20.);
+    }
+
+    #[test]
+    fn combo_builds_up_and_breaks_after_a_while() {
+        let mut combo = Combo::default();
+        let mut time = Time::<()>::default();
+        assert_eq!(combo.multiplier(), 1.);
+
+        assert_eq!(combo.add(Entity::from_raw(0)), 1);
+        assert_eq!(combo.add(Entity::from_raw(0)), 2);
+        assert_eq!(combo.add(Entity::from_raw(1)), 1);
+        assert!((combo.multiplier() - 1.3).abs() < 1e-6);
+
+        time.advance_by(Duration::from_secs(2));
+        combo.tick(&time);
+        assert_eq!(combo.count, 3);
+        time.advance_by(Duration::from_secs(2));
+        combo.tick(&time);
+        assert_eq!(combo.count, 0);
+        assert_eq!(combo.best, 3);
+        // a new combo counts the arrow's kills from scratch
+        assert_eq!(combo.add(Entity::from_raw(0)), 1);
+    }
+}
@@ -0,0 +1,204 @@
+use bevy::hierarchy::{BuildChildren, DespawnRecursiveExt};
+use bevy::input::{
+    gamepad::{GamepadButton, GamepadButtonType},
+    keyboard::KeyCode,
+    mouse::MouseButton,
+    ButtonInput,
+};
+use bevy::prelude::{
+    default, Commands, Component, Entity, NextState, Query, Res, ResMut, State, States, With,
+};
+use bevy::render::color::Color;
+use bevy::text::{TextSection, TextStyle};
+use bevy::ui::{
+    node_bundles::{NodeBundle, TextBundle},
+    AlignItems, BackgroundColor, FlexDirection, JustifyContent, Style, Val,
+};
+
+use crate::controls::ControlsUi;
+use crate::fatigue::FatigueUi;
+use crate::input::RebindingUi;
+use crate::mode::{summary, GameMode, ModeChoiceUi, Run};
+use crate::pull::PullModeUi;
+use crate::stats::{NewHighScore, Stats};
+use crate::wave::WaveState;
+
+use super::Scoreboard;
+
+const TITLE_FONT_SIZE: f32 = 60.;
+const LINE_FONT_SIZE: f32 = 30.;
+
+/// Where the player is in the game
+#[derive(States, Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GameState {
+    #[default]
+    Menu,
+    Playing,
+    /// The game is frozen, gameplay only runs while `Playing`
+    Paused,
+    GameOver,
+}
+
+/// Entities of a running game, despawned once the game over screen is left
+#[derive(Component)]
+pub struct InGame;
+
+#[derive(Component)]
+pub struct MenuUi;
+
+#[derive(Component)]
+pub struct PauseUi;
+
+#[derive(Component)]
+pub struct GameOverUi;
+
+/// Despawns every entity with `T` and its children
+pub fn despawn_all<T: Component>(mut commands: Commands, query: Query<Entity, With<T>>) {
+    for entity in &query {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// A title with some lines below it in the middle of a darkened screen
+fn spawn_screen(
+    commands: &mut Commands,
+    marker: impl Component,
+    title: &str,
+    lines: &[String],
+) -> Entity {
+    commands
+        .spawn((
+            marker,
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    flex_direction: FlexDirection::Column,
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: BackgroundColor(Color::rgba(0., 0., 0., 0.5)),
+                ..default()
+            },
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                title,
+                TextStyle {
+                    font_size: TITLE_FONT_SIZE,
+                    color: Color::WHITE,
+                    ..default()
+                },
+            ));
+            for text in lines {
+                parent.spawn(line(text));
+            }
+        })
+        .id()
+}
+
+fn line(text: &str) -> TextBundle {
+    TextBundle::from_section(
+        text,
+        TextStyle {
+            font_size: LINE_FONT_SIZE,
+            color: Color::WHITE,
+            ..default()
+        },
+    )
+}
+
+pub fn show_menu(mut commands: Commands, stats: Res<Stats>) {
+    let mut lines = stats.high_score_lines(None);
+    lines.extend(stats.lifetime_lines());
+    lines.push("Click or press Enter to start".into());
+    lines.push("Choose a mode with 1, 2 or 3".into());
+    let menu = spawn_screen(&mut commands, MenuUi, "Bevy Bow", &lines);
+    // filled in by `update_mode_choice`
+    let choices = GameMode::ALL.map(|_| {
+        TextSection::from_style(TextStyle {
+            font_size: LINE_FONT_SIZE,
+            color: Color::WHITE,
+            ..default()
+        })
+    });
+    commands.entity(menu).with_children(|parent| {
+        parent.spawn((ModeChoiceUi, TextBundle::from_sections(choices)));
+        // filled in by the `update_*_ui` systems of the settings
+        parent.spawn((FatigueUi, line("")));
+        parent.spawn((ControlsUi, line("")));
+        parent.spawn((PullModeUi, line("")));
+        parent.spawn((RebindingUi, line("")));
+    });
+}
+
+pub fn show_pause(mut commands: Commands) {
+    spawn_screen(
+        &mut commands,
+        PauseUi,
+        "Paused",
+        &[
+            "Press Esc or P to resume".into(),
+            "Press Q to give up".into(),
+        ],
+    );
+}
+
+pub fn show_game_over(
+    mut commands: Commands,
+    score: Res<Scoreboard>,
+    mode: Res<GameMode>,
+    run: Res<Run>,
+    waves: Res<WaveState>,
+    stats: Res<Stats>,
+    new_high_score: Res<NewHighScore>,
+) {
+    let mut lines = summary(*mode, &run, **score, waves.wave);
+    if new_high_score.0.is_some() {
+        lines.push("New high score!".into());
+    }
+    lines.extend(stats.high_score_lines(new_high_score.0));
+    lines.push("Click or press Enter to return to the menu".into());
+    spawn_screen(&mut commands, GameOverUi, "Game over", &lines);
+}
+
+/// Moves between the states on key presses and clicks
+pub fn navigate(
+    state: Res<State<GameState>>,
+    mut next: ResMut<NextState<GameState>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    buttons: Res<ButtonInput<MouseButton>>,
+    gamepad: Res<ButtonInput<GamepadButton>>,
+) {
+    let gamepad_pressed = |button_types: &[GamepadButtonType]| {
+        gamepad
+            .get_just_pressed()
+            .any(|button| button_types.contains(&button.button_type))
+    };
+    // released, so the click doesn't start drawing the bow right away
+    let confirmed = keys.any_just_pressed([KeyCode::Enter, KeyCode::Space])
+        || buttons.just_released(MouseButton::Left)
+        || gamepad_pressed(&[GamepadButtonType::South, GamepadButtonType::Start]);
+    let pause = keys.any_just_pressed([KeyCode::Escape, KeyCode::KeyP])
+        || gamepad_pressed(&[GamepadButtonType::Start]);
+
+    match state.get() {
+        GameState::Menu if confirmed => next.set(GameState::Playing),
+        GameState::Playing if pause => next.set(GameState::Paused),
+        GameState::Paused if pause => next.set(GameState::Playing),
+        GameState::Paused
+            if keys.just_pressed(KeyCode::KeyQ)
+                || gamepad_pressed(&[GamepadButtonType::Select]) =>
+        {
+            next.set(GameState::GameOver)
+        }
+        GameState::GameOver if confirmed => next.set(GameState::Menu),
+        _ => {}
+    }
+}
+
+/// A replay starts playing right away
+pub fn skip_menu(mut next: ResMut<NextState<GameState>>) {
+    next.set(GameState::Playing);
+}
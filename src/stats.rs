@@ -0,0 +1,286 @@
+use std::path::{Path, PathBuf};
+
+use bevy::log::{error, info, warn};
+use bevy::prelude::{Res, ResMut, Resource};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::cli::data_dir;
+use crate::mode::{GameMode, Run};
+use crate::score::Combo;
+use crate::wave::WaveState;
+
+use super::Scoreboard;
+
+/// How many entries the high score table keeps
+const HIGH_SCORES: usize = 5;
+const STATS_FILE: &str = "stats.ron";
+
+/// One finished game in the high score table
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct HighScore {
+    pub score: u32,
+    pub mode: GameMode,
+    /// 0-based index of the wave the game ended in
+    pub wave: usize,
+}
+
+/// Numbers over every game ever played
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, Default)]
+#[serde(default)]
+pub struct Lifetime {
+    pub games: u32,
+    pub shots: u32,
+    /// Shots that hit at least one enemy
+    pub hits: u32,
+    pub kills: u32,
+    /// The furthest an arrow flew before hitting something
+    pub longest_shot: f32,
+    pub best_combo: u32,
+}
+
+impl Lifetime {
+    /// Hits per shot, a shot hits once however many of the arrows it splits into hit
+    pub fn accuracy(&self) -> f32 {
+        if self.shots == 0 {
+            return 0.;
+        }
+        self.hits as f32 / self.shots as f32
+    }
+}
+
+/// High scores and lifetime stats, kept in the user data directory between sessions
+#[derive(Resource, Serialize, Deserialize, Clone, PartialEq, Debug, Default)]
+#[serde(default)]
+pub struct Stats {
+    /// Best first
+    pub high_scores: Vec<HighScore>,
+    pub lifetime: Lifetime,
+}
+
+#[derive(Debug, Error)]
+pub enum StatsError {
+    #[error("could not read or write the stats: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("could not parse the stats: {0}")]
+    Parse(#[from] ron::error::SpannedError),
+    #[error("could not serialize the stats: {0}")]
+    Serialize(#[from] ron::Error),
+}
+
+impl Stats {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, StatsError> {
+        let bytes = std::fs::read(path)?;
+        Ok(ron::de::from_bytes(&bytes)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), StatsError> {
+        if let Some(dir) = path.as_ref().parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let text = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        std::fs::write(path, text)?;
+        Ok(())
+    }
+
+    /// The stats at `path`, fresh ones if there are none yet or they can't be read.
+    /// A corrupt file is moved aside instead of being overwritten by the next save
+    pub fn load_or_default(path: &Path) -> Self {
+        match Stats::load(path) {
+            Ok(stats) => {
+                info!("loaded stats from {}", path.display());
+                stats
+            }
+            Err(StatsError::Io(err)) if err.kind() == std::io::ErrorKind::NotFound => {
+                Stats::default()
+            }
+            Err(err) => {
+                let backup = path.with_extension("ron.corrupt");
+                warn!("{err}, starting over and keeping the old file as {backup:?}");
+                if let Err(err) = std::fs::rename(path, &backup) {
+                    error!("could not move the stats aside: {err}");
+                }
+                Stats::default()
+            }
+        }
+    }
+
+    /// Adds a finished game, returns its 0-based place if it made the high score table
+    pub fn record(&mut self, run: &Run, score: HighScore, best_combo: u32) -> Option<usize> {
+        let lifetime = &mut self.lifetime;
+        lifetime.games += 1;
+        lifetime.shots += run.shots;
+        lifetime.hits += run.hits;
+        lifetime.kills += run.kills;
+        lifetime.longest_shot = lifetime.longest_shot.max(run.longest_shot);
+        lifetime.best_combo = lifetime.best_combo.max(best_combo);
+
+        // later games rank below earlier ones with the same score
+        let place = self
+            .high_scores
+            .iter()
+            .position(|entry| entry.score < score.score)
+            .unwrap_or(self.high_scores.len());
+        if place >= HIGH_SCORES {
+            return None;
+        }
+        self.high_scores.insert(place, score);
+        self.high_scores.truncate(HIGH_SCORES);
+        Some(place)
+    }
+
+    /// The lines of the high score table, `highlight` marks a 0-based place
+    pub fn high_score_lines(&self, highlight: Option<usize>) -> Vec<String> {
+        if self.high_scores.is_empty() {
+            return vec!["No high scores yet".into()];
+        }
+        let mut lines = vec!["High scores".to_string()];
+        for (place, entry) in self.high_scores.iter().enumerate() {
+            let marker = if highlight == Some(place) { ">" } else { " " };
+            lines.push(format!(
+                "{marker} {}. {}  wave {}  {}",
+                place + 1,
+                entry.score,
+                entry.wave + 1,
+                entry.mode.describe()
+            ));
+        }
+        lines
+    }
+
+    pub fn lifetime_lines(&self) -> Vec<String> {
+        let lifetime = &self.lifetime;
+        vec![
+            format!(
+                "Games: {}  Shots: {}  Hits: {}  Accuracy: {:.0}%",
+                lifetime.games,
+                lifetime.shots,
+                lifetime.hits,
+                lifetime.accuracy() * 100.
+            ),
+            format!(
+                "Longest shot: {:.0}  Best combo: {}",
+                lifetime.longest_shot, lifetime.best_combo
+            ),
+        ]
+    }
+}
+
+/// Where the stats are saved, nowhere if there is no user data directory
+#[derive(Resource, Clone, Debug, Default)]
+pub struct StatsPath(pub Option<PathBuf>);
+
+impl StatsPath {
+    pub fn from_args(args: &[String]) -> Self {
+        StatsPath(data_dir(args).map(|dir| dir.join(STATS_FILE)))
+    }
+}
+
+/// The place the last game took in the high score table, shown on the game over screen
+#[derive(Resource, Default)]
+pub struct NewHighScore(pub Option<usize>);
+
+pub fn load_stats(path: Res<StatsPath>, mut stats: ResMut<Stats>) {
+    if let Some(path) = &path.0 {
+        *stats = Stats::load_or_default(path);
+    }
+}
+
+/// Adds the finished game to the stats and saves them right away
+#[allow(clippy::too_many_arguments)]
+pub fn record_game(
+    path: Res<StatsPath>,
+    mode: Res<GameMode>,
+    run: Res<Run>,
+    score: Res<Scoreboard>,
+    waves: Res<WaveState>,
+    combo: Res<Combo>,
+    mut stats: ResMut<Stats>,
+    mut new_high_score: ResMut<NewHighScore>,
+) {
+    let entry = HighScore {
+        score: **score,
+        mode: *mode,
+        wave: waves.wave,
+    };
+    new_high_score.0 = stats.record(&run, entry, combo.best);
+    if let Some(path) = &path.0 {
+        if let Err(err) = stats.save(path) {
+            error!("{err}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh path in the temp dir, nothing is written to the real user data directory
+    fn temp_stats(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("bevy-bow-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir.join("stats.ron")
+    }
+
+    fn high_score(score: u32) -> HighScore {
+        HighScore {
+            score,
+            mode: GameMode::default(),
+            wave: 0,
+        }
+    }
+
+    #[test]
+    fn stats_survive_a_round_trip() {
+        let path = temp_stats("round-trip");
+        let mut stats = Stats::default();
+        let run = Run {
+            shots: 10,
+            hits: 4,
+            kills: 3,
+            longest_shot: 612.5,
+            ..Run::new(GameMode::Timed(60.))
+        };
+        stats.record(&run, high_score(42), 3);
+        stats.record(&run, high_score(7), 5);
+
+        stats.save(&path).unwrap();
+        let loaded = Stats::load(&path).unwrap();
+
+        assert_eq!(loaded, stats);
+        assert_eq!(loaded.lifetime.shots, 20);
+        assert_eq!(loaded.lifetime.accuracy(), 0.4);
+        assert_eq!(loaded.lifetime.longest_shot, 612.5);
+        assert_eq!(loaded.lifetime.best_combo, 5);
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn corrupt_stats_are_moved_aside() {
+        let path = temp_stats("corrupt");
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, "(high_scores: [oops").unwrap();
+
+        assert_eq!(Stats::load_or_default(&path), Stats::default());
+        assert!(!path.exists());
+        assert!(path.with_extension("ron.corrupt").exists());
+        // no file at all is just a first start
+        assert_eq!(Stats::load_or_default(&path), Stats::default());
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn high_score_table_keeps_the_best_games() {
+        let mut stats = Stats::default();
+        let run = Run::default();
+        for score in [10, 30, 20, 50, 40] {
+            stats.record(&run, high_score(score), 0);
+        }
+        assert_eq!(stats.record(&run, high_score(35), 0), Some(2));
+        assert_eq!(stats.record(&run, high_score(5), 0), None);
+
+        let scores: Vec<u32> = stats.high_scores.iter().map(|entry| entry.score).collect();
+        assert_eq!(scores, vec![50, 40, 35, 30, 20]);
+        assert_eq!(stats.lifetime.games, 7);
+    }
+}
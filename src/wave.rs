@@ -0,0 +1,395 @@
+use bevy::asset::{Asset, AssetEvent, Assets, Handle};
+use bevy::log::error;
+use bevy::prelude::{
+    Deref, Event, EventReader, EventWriter, Query, Reflect, Res, ResMut, Resource, With,
+};
+use bevy::reflect::TypePath;
+use bevy::time::{Time, Timer, TimerMode};
+use serde::Deserialize;
+
+use crate::archetype::{EnemyArchetype, EnemyArchetypes};
+use crate::rng::GameRng;
+
+use super::{Enemy, EnemyArchetypesHandle};
+
+/// The waves of a game and how they get harder after the last one
+#[derive(Asset, TypePath, Deserialize, Debug)]
+pub struct Waves {
+    /// How many enemies may be in the air at the same time
+    pub max_enemies: usize,
+    pub waves: Vec<Wave>,
+    pub ramp: Ramp,
+}
+
+/// A number of spawns, the wave is over once all of them are shot down
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub struct Wave {
+    /// How many times an archetype gets spawned, a whole flock counts once
+    pub count: u32,
+    /// Seconds between two spawns
+    pub interval: f32,
+    /// Scales the speed of every spawned enemy
+    #[serde(default = "no_speedup")]
+    pub speed: f32,
+    /// Archetype names and how often they show up.
+    /// Every archetype by its own weight if empty or none of the names is an archetype
+    #[serde(default)]
+    pub mix: Vec<(String, f32)>,
+    /// Seconds of quiet before the wave starts
+    pub intermission: f32,
+}
+
+fn no_speedup() -> f32 {
+    1.
+}
+
+/// How much harder each wave after the last defined one gets
+#[derive(Deserialize, Clone, Debug)]
+pub struct Ramp {
+    /// Extra spawns per wave
+    pub count: u32,
+    /// The interval gets multiplied by this every wave
+    pub interval: f32,
+    pub min_interval: f32,
+    /// Extra speed scale per wave
+    pub speed: f32,
+    /// Weight these archetypes gain per wave on top of the last wave's mix,
+    /// so they show up more and more often
+    #[serde(default)]
+    pub mix: Vec<(String, f32)>,
+}
+
+impl Waves {
+    /// The wave with the 0-based `index`, waves past the defined ones ramp up from the last one
+    pub fn get(&self, index: usize) -> Option<Wave> {
+        if let Some(wave) = self.waves.get(index) {
+            return Some(wave.clone());
+        }
+        let last = self.waves.last()?;
+        let n = index + 1 - self.waves.len();
+        let mut mix = last.mix.clone();
+        for (name, weight) in &self.ramp.mix {
+            let gained = weight * n as f32;
+            match mix.iter_mut().find(|(old, _)| old == name) {
+                Some((_, old)) => *old += gained,
+                None => mix.push((name.clone(), gained)),
+            }
+        }
+        Some(Wave {
+            count: last.count + self.ramp.count * n as u32,
+            interval: (last.interval * self.ramp.interval.powi(n as i32))
+                .max(self.ramp.min_interval),
+            speed: last.speed + self.ramp.speed * n as f32,
+            mix,
+            ..last.clone()
+        })
+    }
+
+    /// The names in the mixes that no archetype has, those never show up
+    pub fn unknown_archetypes<'a>(
+        &'a self,
+        archetypes: &'a EnemyArchetypes,
+    ) -> impl Iterator<Item = &'a str> {
+        self.waves
+            .iter()
+            .flat_map(|wave| &wave.mix)
+            .chain(&self.ramp.mix)
+            .map(|(name, _)| name.as_str())
+            .filter(|name| !archetypes.0.iter().any(|archetype| archetype.name == *name))
+    }
+}
+
+#[derive(Resource, Deref)]
+pub struct WavesHandle(pub Handle<Waves>);
+
+/// Where the game is in the current wave
+#[derive(Resource, Reflect, Default)]
+pub struct WaveState {
+    /// 0-based index of the current wave
+    pub wave: usize,
+    /// The spawns of the current wave that made it into the sky
+    pub spawned: u32,
+    pub phase: WavePhase,
+}
+
+#[derive(Reflect, Default, Clone, PartialEq, Debug)]
+pub enum WavePhase {
+    /// About to start the intermission before the current wave
+    #[default]
+    Starting,
+    Intermission(Timer),
+    Spawning(Timer),
+    /// Everything is spawned, waiting for the last enemies to be shot down
+    Clearing,
+}
+
+/// Asks for an enemy of `archetype` to be spawned, `speed` scales its speed.
+/// The spawn counts for the wave once the enemy is there
+#[derive(Event)]
+pub struct SpawnEnemyEvent {
+    pub archetype: EnemyArchetype,
+    pub speed: f32,
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn run_waves(
+    time: Res<Time>,
+    mut rng: ResMut<GameRng>,
+    mut state: ResMut<WaveState>,
+    waves: Res<Assets<Waves>>,
+    waves_handle: Res<WavesHandle>,
+    archetypes: Res<Assets<EnemyArchetypes>>,
+    archetypes_handle: Res<EnemyArchetypesHandle>,
+    enemies: Query<(), With<Enemy>>,
+    mut spawns: EventWriter<SpawnEnemyEvent>,
+) {
+    let (Some(waves), Some(archetypes)) = (
+        waves.get(&**waves_handle),
+        archetypes.get(&**archetypes_handle),
+    ) else {
+        return;
+    };
+    let Some(wave) = waves.get(state.wave) else {
+        return;
+    };
+
+    let state = &mut *state;
+    match &mut state.phase {
+        WavePhase::Starting => {
+            state.spawned = 0;
+            state.phase =
+                WavePhase::Intermission(Timer::from_seconds(wave.intermission, TimerMode::Once));
+        }
+        WavePhase::Intermission(timer) => {
+            timer.tick(time.delta());
+            if timer.finished() {
+                state.phase =
+                    WavePhase::Spawning(Timer::from_seconds(wave.interval, TimerMode::Repeating));
+            }
+        }
+        WavePhase::Spawning(_) if state.spawned >= wave.count => {
+            state.phase = WavePhase::Clearing;
+        }
+        WavePhase::Spawning(timer) => {
+            timer.tick(time.delta());
+            // a full sky delays the spawn until the next interval
+            if !timer.just_finished() || enemies.iter().count() >= waves.max_enemies {
+                return;
+            }
+            // a spawn that fails is tried again at the next interval
+            if let Some(archetype) = archetypes.choose_from(&wave.mix, &mut **rng) {
+                spawns.send(SpawnEnemyEvent {
+                    archetype: archetype.clone(),
+                    speed: wave.speed,
+                });
+            }
+        }
+        WavePhase::Clearing => {
+            if enemies.is_empty() {
+                state.wave += 1;
+                state.phase = WavePhase::Starting;
+            }
+        }
+    }
+}
+
+/// Complains about mix names without an archetype whenever the waves or the archetypes load
+pub fn check_waves(
+    mut wave_events: EventReader<AssetEvent<Waves>>,
+    mut archetype_events: EventReader<AssetEvent<EnemyArchetypes>>,
+    waves: Res<Assets<Waves>>,
+    waves_handle: Res<WavesHandle>,
+    archetypes: Res<Assets<EnemyArchetypes>>,
+    archetypes_handle: Res<EnemyArchetypesHandle>,
+) {
+    // both readers are read to the end, or old events would come up next time
+    let waves_loaded = wave_events.read().filter(loaded).count() > 0;
+    let archetypes_loaded = archetype_events.read().filter(loaded).count() > 0;
+    if !waves_loaded && !archetypes_loaded {
+        return;
+    }
+    let (Some(waves), Some(archetypes)) = (
+        waves.get(&**waves_handle),
+        archetypes.get(&**archetypes_handle),
+    ) else {
+        return;
+    };
+    for name in waves.unknown_archetypes(archetypes) {
+        error!("the waves mix in \"{name}\", but no enemy archetype is called that");
+    }
+}
+
+fn loaded<A: Asset>(event: &&AssetEvent<A>) -> bool {
+    matches!(
+        event,
+        AssetEvent::LoadedWithDependencies { .. } | AssetEvent::Modified { .. }
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::app::{App, Update};
+    use bevy::ecs::schedule::IntoSystemConfigs;
+    use bevy::utils::Duration;
+
+    use super::*;
+    use crate::rng::Seed;
+    use crate::tests::test_app;
+
+    #[test]
+    fn shipped_waves_parse() {
+        let waves: Waves = ron::de::from_str(include_str!("../assets/default.waves.ron")).unwrap();
+        let archetypes: EnemyArchetypes =
+            ron::de::from_str(include_str!("../assets/enemies.archetypes.ron")).unwrap();
+        assert_eq!(waves.unknown_archetypes(&archetypes).count(), 0);
+    }
+
+    fn waves(max_enemies: usize) -> Waves {
+        Waves {
+            max_enemies,
+            waves: vec![Wave {
+                count: 3,
+                interval: 1.,
+                speed: 1.,
+                mix: vec![],
+                intermission: 2.,
+            }],
+            ramp: Ramp {
+                count: 2,
+                interval: 0.5,
+                min_interval: 0.3,
+                speed: 0.1,
+                mix: vec![],
+            },
+        }
+    }
+
+    #[test]
+    fn waves_ramp_up_after_the_last_one() {
+        let waves = waves(10);
+        assert_eq!(waves.get(0), Some(waves.waves[0].clone()));
+
+        let third = waves.get(2).unwrap();
+        assert_eq!(third.count, 7);
+        assert_eq!(third.interval, 0.3);
+        assert!((third.speed - 1.2).abs() < 1e-6);
+    }
+
+    #[test]
+    fn mix_shifts_towards_the_ramp_archetypes() {
+        let mut waves = waves(10);
+        waves.waves[0].mix = vec![("Bird".to_string(), 3.), ("Swift".to_string(), 1.)];
+        waves.ramp.mix = vec![("Swift".to_string(), 1.), ("Vulture".to_string(), 0.5)];
+
+        assert_eq!(waves.get(0).unwrap().mix, waves.waves[0].mix);
+        assert_eq!(
+            waves.get(2).unwrap().mix,
+            vec![
+                ("Bird".to_string(), 3.),
+                ("Swift".to_string(), 3.),
+                ("Vulture".to_string(), 1.),
+            ]
+        );
+    }
+
+    #[test]
+    fn mixes_with_unknown_archetypes_are_found() {
+        let archetypes: EnemyArchetypes =
+            ron::de::from_str(include_str!("../assets/enemies.archetypes.ron")).unwrap();
+        let missing = vec![("Missing".to_string(), 1.)];
+        let waves = Waves {
+            waves: vec![Wave {
+                mix: missing,
+                ..waves(1).waves[0].clone()
+            }],
+            ..waves(1)
+        };
+        assert_eq!(
+            waves.unknown_archetypes(&archetypes).collect::<Vec<_>>(),
+            ["Missing"]
+        );
+    }
+
+    /// Stands in for `spawn_enemy`, every spawn makes it into the sky
+    fn land_spawns(mut spawns: EventReader<SpawnEnemyEvent>, mut state: ResMut<WaveState>) {
+        state.spawned += spawns.read().count() as u32;
+    }
+
+    fn wave_app(max_enemies: usize, spawns_land: bool) -> App {
+        let mut app = test_app(Duration::from_millis(100));
+        app.insert_resource(GameRng::new(Seed(1)))
+            .init_resource::<WaveState>()
+            .init_resource::<Assets<Waves>>()
+            .init_resource::<Assets<EnemyArchetypes>>()
+            .add_event::<SpawnEnemyEvent>()
+            .add_systems(Update, run_waves);
+        if spawns_land {
+            app.add_systems(Update, land_spawns.after(run_waves));
+        }
+
+        let archetypes: EnemyArchetypes =
+            ron::de::from_str(include_str!("../assets/enemies.archetypes.ron")).unwrap();
+        let archetypes = app
+            .world
+            .resource_mut::<Assets<EnemyArchetypes>>()
+            .add(archetypes);
+        let waves = app
+            .world
+            .resource_mut::<Assets<Waves>>()
+            .add(waves(max_enemies));
+        app.insert_resource(EnemyArchetypesHandle(archetypes))
+            .insert_resource(WavesHandle(waves));
+        app
+    }
+
+    fn spawned(app: &App) -> u32 {
+        app.world.resource::<WaveState>().spawned
+    }
+
+    #[test]
+    fn wave_spawns_after_the_intermission_and_moves_on_once_cleared() {
+        let mut app = wave_app(10, true);
+        // nothing spawns real enemies here, this one keeps the wave from being cleared
+        let enemy = app.world.spawn(Enemy).id();
+        for _ in 0..19 {
+            app.update();
+        }
+        assert_eq!(spawned(&app), 0);
+
+        for _ in 0..35 {
+            app.update();
+        }
+        assert_eq!(spawned(&app), 3);
+        assert_eq!(app.world.resource::<WaveState>().phase, WavePhase::Clearing);
+
+        app.update();
+        assert_eq!(app.world.resource::<WaveState>().wave, 0);
+
+        app.world.despawn(enemy);
+        app.update();
+        assert_eq!(app.world.resource::<WaveState>().wave, 1);
+    }
+
+    #[test]
+    fn full_sky_holds_back_spawns() {
+        let mut app = wave_app(1, true);
+        app.world.spawn(Enemy);
+        for _ in 0..60 {
+            app.update();
+        }
+        assert_eq!(spawned(&app), 0);
+    }
+
+    #[test]
+    fn failed_spawns_do_not_count() {
+        let mut app = wave_app(10, false);
+        for _ in 0..60 {
+            app.update();
+        }
+        assert_eq!(spawned(&app), 0);
+        assert!(matches!(
+            app.world.resource::<WaveState>().phase,
+            WavePhase::Spawning(_)
+        ));
+    }
+}